@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+
+use sqids::Sqids;
+use utoipa::{
+    PartialSchema,
+    ToSchema,
+    openapi::{
+        RefOr,
+        Schema,
+        Type,
+        schema::{
+            ObjectBuilder,
+            SchemaType,
+        },
+    },
+};
+
+// `Path<T>`'s `Deserialize` impl has no access to `AppState`, so the codec
+// lives here instead: configured once from env at startup, same deployment
+// lifetime as `AppState` itself, just not re-cloned on every request.
+static CODEC: OnceLock<Sqids> = OnceLock::new();
+
+/// Configures the process-wide id codec from `ID_ALPHABET` (a permutation of
+/// sqids' default alphabet); falls back to the stock alphabet if unset. Must
+/// run once at startup, before the router handles its first request.
+pub fn init(alphabet: Option<String>) -> Result<(), String> {
+    let options = sqids::Options {
+        alphabet: alphabet.map_or_else(|| sqids::Options::default().alphabet, |a| a.chars().collect()),
+        ..Default::default()
+    };
+
+    CODEC
+        .set(Sqids::new(Some(options)).map_err(|e| e.to_string())?)
+        .map_err(|_| "encoded_id::init called more than once".to_owned())
+}
+
+fn codec() -> &'static Sqids {
+    #[allow(clippy::expect_used)]
+    CODEC.get().expect("encoded_id::init must run before the router handles requests")
+}
+
+/// Opaque, reversible HTTP-facing encoding of an internal `i64` primary key,
+/// so paths like `/profiles/{id}` don't leak sequential record counts.
+///
+/// Decoding never fails at extraction time — every path segment produces an
+/// `EncodedId`, valid or not — so callers `.decode()` it themselves and map a
+/// bad id to their own entity's not-found error, same as a guessed/deleted id.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodedId(Option<i64>);
+
+impl EncodedId {
+    /// The decoded `i64`, or `None` if the path segment wasn't a valid encoded id
+    pub fn decode(self) -> Option<i64> {
+        self.0
+    }
+
+    pub fn encode(id: i64) -> String {
+        codec().encode(&[id.cast_unsigned()]).unwrap_or_default()
+    }
+}
+
+/// Serializes an `i64` primary/foreign key as its encoded id string, for use
+/// with `#[serde(serialize_with = "encoded_id::serialize")]` on response DTOs
+/// whose `id`/`*_id` fields must stay plain `i64` for `sqlx::query_as!` to
+/// type-check, but shouldn't hand the raw integer back out over the wire.
+pub fn serialize<S>(id: &i64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(&EncodedId::encode(*id))
+}
+
+/// Same as [`serialize`], for an optional foreign key like `Device.profile_id`
+pub fn serialize_opt<S>(id: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match id {
+        Some(id) => serializer.serialize_str(&EncodedId::encode(*id)),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for EncodedId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+
+        Ok(Self(match codec().decode(&raw).as_slice() {
+            [id] => i64::try_from(*id).ok(),
+            _ => None,
+        }))
+    }
+}
+
+impl PartialSchema for EncodedId {
+    fn schema() -> RefOr<Schema> {
+        RefOr::T(Schema::Object(
+            ObjectBuilder::new().schema_type(SchemaType::Type(Type::String)).build(),
+        ))
+    }
+}
+
+impl ToSchema for EncodedId {}
@@ -25,6 +25,8 @@ pub enum Error {
     DeviceNameTaken,
     #[error("cannot delete an administrator account")]
     CannotDeleteAnAdmin,
+    #[error("cannot delete the last remaining administrator account")]
+    CannotDeleteLastAdmin,
 
     // NOTE: register tells that username is taken,
     // so it's ok if login tells that username is not found
@@ -33,10 +35,56 @@ pub enum Error {
 
     #[error("profile does not exist")]
     ProfileNotFound,
+    #[error("profile has no latitude/longitude set")]
+    ProfileMissingLocation,
     #[error("device does not exist")]
     DeviceNotFound,
     #[error("telemetry does not exist")]
     TelemetryNotFound,
+    #[error("device command does not exist")]
+    DeviceCommandNotFound,
+    #[error("auth request does not exist")]
+    AuthRequestNotFound,
+
+    #[error("auth request has expired")]
+    AuthRequestExpired,
+    #[error("auth request has not been approved")]
+    AuthRequestNotApproved,
+
+    #[error("two-factor authentication is not enrolled")]
+    TotpNotEnrolled,
+    #[error("two-factor authentication is already enabled")]
+    TotpAlreadyEnabled,
+    #[error("invalid two-factor code")]
+    TotpCodeInvalid,
+
+    #[error("reset token is invalid")]
+    ResetTokenInvalid,
+    #[error("reset token has expired or was already used")]
+    ResetTokenExpired,
+
+    #[error("oauth state parameter is invalid, expired, or does not match")]
+    OAuthStateMismatch,
+    #[error("oauth provider rejected the code exchange or userinfo request: {0}")]
+    OAuthExchangeFailed(String),
+    #[error("unknown oauth provider")]
+    OAuthProviderNotFound,
+
+    #[error("refresh token is invalid, expired, or already used")]
+    RefreshTokenInvalid,
+    #[error("refresh token was already revoked; all sessions for this account have been revoked")]
+    RefreshTokenReused,
+    #[error("session does not exist")]
+    SessionNotFound,
+
+    #[error("invite code does not exist")]
+    InviteNotFound,
+    #[error("invite code has expired")]
+    InviteExpired,
+    #[error("invite code has reached its use limit")]
+    InviteExhausted,
+    #[error("open registration is disabled; an invite code is required")]
+    InviteRequired,
 
     #[error("credentials are wrong")]
     WrongCredentials,
@@ -55,11 +103,21 @@ pub enum Error {
     CannotSetOthersDevicePrivate,
     #[error("users cannot regenerate an owner's device key")]
     CannotChangeDeviceKey,
+    #[error("only a device's owner can set its push token")]
+    CannotSetOthersPushToken,
+    #[error("sub-users cannot view the audit log")]
+    UsersCannotViewAuditLog,
+    #[error("this endpoint is restricted to administrators")]
+    AdminOnly,
+    #[error("this account has been blocked")]
+    AccountBlocked,
 
     #[error(transparent)]
     InvalidData(#[from] garde::Report),
     #[error(transparent)]
     InvalidJson(#[from] axum::extract::rejection::JsonRejection),
+    #[error("unknown aggregation function, expected one of avg/min/max/first/last")]
+    InvalidAggFunction,
 
     // Internal
     #[error("database: {0}")]
@@ -80,30 +138,54 @@ impl From<&Error> for StatusCode {
             Error::UsernameTaken
             | Error::ProfileNameTaken
             | Error::DeviceNameTaken
-            | Error::CannotDeleteAnAdmin => Self::CONFLICT,
+            | Error::CannotDeleteAnAdmin
+            | Error::CannotDeleteLastAdmin => Self::CONFLICT,
 
             Error::WrongCredentials
             | Error::MissingCredentials
             | Error::InvalidToken
-            | Error::TokenExpired => Self::UNAUTHORIZED,
+            | Error::TokenExpired
+            | Error::AuthRequestNotApproved
+            | Error::TotpCodeInvalid
+            | Error::ResetTokenInvalid
+            | Error::ResetTokenExpired
+            | Error::RefreshTokenInvalid
+            | Error::RefreshTokenReused
+            | Error::OAuthStateMismatch => Self::UNAUTHORIZED,
 
             Error::UsersCannotCreateUsers
             | Error::CannotSetOthersProfilePrivate
             | Error::CannotSetOthersDevicePrivate
-            | Error::CannotChangeDeviceKey => Self::FORBIDDEN,
+            | Error::CannotChangeDeviceKey
+            | Error::CannotSetOthersPushToken
+            | Error::UsersCannotViewAuditLog
+            | Error::AccountBlocked
+            | Error::AdminOnly
+            | Error::InviteRequired => Self::FORBIDDEN,
 
             Error::UserNotFound
             | Error::ProfileNotFound
             | Error::DeviceNotFound
-            | Error::TelemetryNotFound => Self::NOT_FOUND,
+            | Error::TelemetryNotFound
+            | Error::DeviceCommandNotFound
+            | Error::AuthRequestNotFound
+            | Error::TotpNotEnrolled
+            | Error::SessionNotFound
+            | Error::OAuthProviderNotFound
+            | Error::InviteNotFound => Self::NOT_FOUND,
 
-            Error::InvalidJson(_) => Self::BAD_REQUEST,
-            Error::InvalidData(_) => Self::UNPROCESSABLE_ENTITY,
+            Error::AuthRequestExpired | Error::InviteExpired => Self::GONE,
+
+            Error::TotpAlreadyEnabled | Error::InviteExhausted => Self::CONFLICT,
+
+            Error::InvalidJson(_) | Error::InvalidAggFunction => Self::BAD_REQUEST,
+            Error::InvalidData(_) | Error::ProfileMissingLocation => Self::UNPROCESSABLE_ENTITY,
 
             Error::Database(_)
             | Error::PasswordHash(_)
             | Error::Jwt(_)
             | Error::DataCorruption(_)
+            | Error::OAuthExchangeFailed(_)
             | Error::InvalidTime(_) => Self::INTERNAL_SERVER_ERROR,
         }
     }
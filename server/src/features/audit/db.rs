@@ -0,0 +1,141 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::Serialize;
+use serde_json::{
+    Value,
+    json,
+};
+use sqlx::{
+    PgPool,
+    types::Json,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    errors::Error,
+    features::auth::Role,
+};
+
+/// One recorded mutation of an audited entity (profile, user, or device)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// The acting user; null if that user's row has since been purged
+    pub actor_user_id: Option<i64>,
+    pub actor_role: String,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: i64,
+    /// Field-level diff of what changed, or a snapshot of what was removed
+    #[schema(value_type = Object)]
+    pub diff: Json<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Records one audited mutation inside the caller's open transaction, so the
+/// audit row commits atomically with the change it describes.
+pub(crate) async fn record(
+    conn: &mut sqlx::PgConnection,
+    actor_user_id: i64,
+    actor_role: Role,
+    action: &str,
+    entity_type: &str,
+    entity_id: i64,
+    diff: Value,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO audit (actor_user_id, actor_role, action, entity_type, entity_id, diff)
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        actor_user_id,
+        actor_role.as_str(),
+        action,
+        entity_type,
+        entity_id,
+        diff
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Compares two JSON snapshots of the same entity and returns a map of
+/// `{field: {"old": ..., "new": ...}}` for fields that differ, for storage
+/// as an audit entry's `diff` payload
+pub(crate) fn diff(before: &Value, after: &Value) -> Value {
+    let (Value::Object(before), Value::Object(after)) = (before, after) else {
+        return json!({ "old": before, "new": after });
+    };
+
+    let mut changed = serde_json::Map::new();
+    for (field, new_value) in after {
+        if before.get(field) != Some(new_value) {
+            changed.insert(field.clone(), json!({ "old": before.get(field), "new": new_value }));
+        }
+    }
+
+    Value::Object(changed)
+}
+
+impl AuditEntry {
+    /// Unrestricted query over the full log, for admins
+    pub async fn list_all(
+        pool: &PgPool,
+        entity_type: Option<String>,
+        entity_id: Option<i64>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT * FROM audit
+               WHERE created_at >= $1 AND created_at < $2
+                 AND ($3::text IS NULL OR entity_type = $3)
+                 AND ($4::bigint IS NULL OR entity_id = $4)
+               ORDER BY created_at DESC"#,
+            start,
+            end,
+            entity_type,
+            entity_id
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /// Query scoped to entries an owner is entitled to see: their own
+    /// account, their own profiles/devices, and anything they personally did
+    pub async fn list_as_owner(
+        pool: &PgPool,
+        owner_id: i64,
+        entity_type: Option<String>,
+        entity_id: Option<i64>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            r#"SELECT * FROM audit
+               WHERE created_at >= $2 AND created_at < $3
+                 AND ($4::text IS NULL OR entity_type = $4)
+                 AND ($5::bigint IS NULL OR entity_id = $5)
+                 AND (
+                    actor_user_id = $1
+                    OR (entity_type = 'user' AND entity_id = $1)
+                    OR (entity_type = 'profile'
+                        AND entity_id IN (SELECT id FROM profiles WHERE owner_id = $1))
+                    OR (entity_type = 'device'
+                        AND entity_id IN (SELECT id FROM devices WHERE owner_id = $1))
+                 )
+               ORDER BY created_at DESC"#,
+            owner_id,
+            start,
+            end,
+            entity_type,
+            entity_id
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+}
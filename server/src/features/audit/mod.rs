@@ -0,0 +1,103 @@
+use axum::{
+    Json,
+    extract::{
+        Query,
+        State,
+    },
+};
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    features::auth::{
+        Authenticated,
+        Role,
+    },
+    responses::ListAuditLog,
+};
+
+mod db;
+
+pub use db::AuditEntry;
+pub(crate) use db::{
+    diff,
+    record,
+};
+
+pub const TAG: &str = "Audit";
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(get_all))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// Restrict to one kind of entity, e.g. `"profile"`, `"user"`, `"device"`
+    #[param(example = "profile")]
+    pub entity_type: Option<String>,
+
+    /// Restrict to a single entity's history
+    pub entity_id: Option<i64>,
+
+    /// Start time for the queried range (RFC3339 format)
+    #[param(example = "2025-12-10T00:00:00Z")]
+    pub start: DateTime<Utc>,
+
+    /// End time for the queried range (RFC3339 format)
+    #[param(example = "2025-12-31T00:00:00Z")]
+    pub end: DateTime<Utc>,
+}
+
+/// List audit log entries
+///
+/// - Admin sees the whole log.
+/// - Owner sees entries for their own account, their profiles/devices, and
+///   anything they personally did.
+#[utoipa::path(
+    get,
+    path = "",
+    params(AuditQuery),
+    responses(ListAuditLog),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn get_all(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, Error> {
+    Ok(Json(match auth.role {
+        Role::Admin => {
+            AuditEntry::list_all(
+                &state.pool,
+                query.entity_type,
+                query.entity_id,
+                query.start,
+                query.end,
+            )
+            .await?
+        }
+        Role::Owner => {
+            AuditEntry::list_as_owner(
+                &state.pool,
+                auth.id,
+                query.entity_type,
+                query.entity_id,
+                query.start,
+                query.end,
+            )
+            .await?
+        }
+        Role::User(_) => return Err(Error::UsersCannotViewAuditLog),
+    }))
+}
@@ -12,7 +12,10 @@ use sqlx::{
 };
 use utoipa::ToSchema;
 
-use crate::errors::Error;
+use crate::{
+    errors::Error,
+    features::audit,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
@@ -21,7 +24,19 @@ pub enum Role {
     Owner,
     User(i64),
 }
-#[derive(Serialize, ToSchema)]
+
+impl Role {
+    /// Flattens the role to its bare kind, e.g. for storage in the `audit` table
+    /// where a `User(parent_id)`'s carried id isn't relevant
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Admin => "admin",
+            Self::Owner => "owner",
+            Self::User(_) => "user",
+        }
+    }
+}
+#[derive(Clone, Serialize, ToSchema)]
 pub struct User {
     pub id: i64,
     pub username: String,
@@ -29,6 +44,10 @@ pub struct User {
     pub password_hash: String,
     pub role: Role,
     pub created_at: DateTime<Utc>,
+    /// When this account was soft-deleted, if at all
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Frozen by an admin/owner; blocks login and invalidates existing sessions
+    pub blocked: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Type)]
@@ -45,6 +64,8 @@ struct DbUser {
     pub role: DbRole,
     pub parent_id: Option<i64>,
     pub created_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub blocked: bool,
 }
 
 impl TryFrom<DbUser> for User {
@@ -65,6 +86,8 @@ impl TryFrom<DbUser> for User {
             password_hash: value.password_hash,
             role,
             created_at: value.created_at,
+            deleted_at: value.deleted_at,
+            blocked: value.blocked,
         })
     }
 }
@@ -83,13 +106,15 @@ impl From<User> for DbUser {
             role,
             parent_id,
             created_at: value.created_at,
+            deleted_at: value.deleted_at,
+            blocked: value.blocked,
         }
     }
 }
 
 impl User {
     pub async fn create(
-        pool: &PgPool,
+        executor: impl sqlx::PgExecutor<'_>,
         username: &str,
         password_hash: &str,
         role: Role,
@@ -105,56 +130,135 @@ impl User {
             r#"INSERT INTO users (username, password_hash, role, parent_id)
                VALUES ($1, $2, $3, $4)
                RETURNING id, username, password_hash, role AS "role: DbRole",
-                         parent_id, created_at"#,
+                         parent_id, created_at, deleted_at, blocked"#,
             username,
             password_hash,
             role as DbRole,
             parent_id
         )
-        .fetch_one(pool)
+        .fetch_one(executor)
         .await?;
 
         user.try_into()
     }
 
-    pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Self, Error> {
+    /// Cheap blocked-state check, re-run on every authenticated request so a
+    /// freshly blocked account's already-issued JWT stops working immediately
+    pub async fn is_blocked(pool: &PgPool, id: i64) -> Result<bool, Error> {
+        Ok(sqlx::query_scalar!(
+            "SELECT blocked FROM users WHERE id = $1 AND deleted_at IS NULL",
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(true))
+    }
+}
+
+/// Persistence surface for [`User`], extracted so a non-Postgres backend can
+/// be plugged in instead of this crate hard-coding `PgPool` everywhere. The
+/// Postgres implementation lives behind the `postgres` feature; [`User`]'s
+/// inherent methods are thin generic wrappers so existing call sites
+/// (already passing `&state.pool`) don't need to change.
+///
+/// `User::create` is deliberately NOT part of this trait: it takes a generic
+/// `impl sqlx::PgExecutor<'_>` so it can run inside an in-progress
+/// transaction (e.g. invite redemption), and that executor bound has no
+/// backend-agnostic equivalent here.
+#[allow(async_fn_in_trait)]
+pub trait UserStore {
+    async fn get_by_id(&self, id: i64) -> Result<User, Error>;
+    async fn get_by_username(&self, username: &str) -> Result<User, Error>;
+    /// Paginated, optionally role/blocked-filtered listing, for the admin user-management API
+    async fn list(
+        &self,
+        role: Option<String>,
+        blocked: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<User>, Error>;
+    async fn update<F>(&self, actor_user_id: i64, actor_role: Role, id: i64, func: F) -> Result<User, Error>
+    where
+        F: FnOnce(&mut User) -> Result<bool, Error>;
+    /// Soft-deletes the account by setting `deleted_at`, so it disappears
+    /// from `get_by_id`/`get_by_username` without losing the row
+    async fn delete(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error>;
+    /// Clears `deleted_at` on a soft-deleted account
+    async fn restore(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<User, Error>;
+    /// Permanently removes a soft-deleted account's row
+    async fn purge(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error>;
+}
+
+#[cfg(feature = "postgres")]
+impl UserStore for PgPool {
+    async fn get_by_id(&self, id: i64) -> Result<User, Error> {
         sqlx::query_as!(
             DbUser,
             r#"SELECT id, username, password_hash, role AS "role: DbRole",
-                      parent_id, created_at
-               FROM users WHERE id = $1"#,
+                      parent_id, created_at, deleted_at, blocked
+               FROM users WHERE id = $1 AND deleted_at IS NULL"#,
             id,
         )
-        .fetch_optional(pool)
+        .fetch_optional(self)
         .await?
         .ok_or(Error::UserNotFound)?
         .try_into()
     }
-    pub async fn get_by_username(pool: &PgPool, username: &str) -> Result<Self, Error> {
+
+    async fn get_by_username(&self, username: &str) -> Result<User, Error> {
         sqlx::query_as!(
             DbUser,
             r#"SELECT id, username, password_hash, role AS "role: DbRole",
-                      parent_id, created_at
-               FROM users WHERE username = $1"#,
+                      parent_id, created_at, deleted_at, blocked
+               FROM users WHERE username = $1 AND deleted_at IS NULL"#,
             username,
         )
-        .fetch_optional(pool)
+        .fetch_optional(self)
         .await?
         .ok_or(Error::UserNotFound)?
         .try_into()
     }
 
-    pub async fn update<F>(pool: &PgPool, id: i64, func: F) -> Result<Self, Error>
+    async fn list(
+        &self,
+        role: Option<String>,
+        blocked: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<User>, Error> {
+        sqlx::query_as!(
+            DbUser,
+            r#"SELECT id, username, password_hash, role AS "role: DbRole",
+                      parent_id, created_at, deleted_at, blocked
+               FROM users
+               WHERE deleted_at IS NULL
+                 AND ($1::text IS NULL OR role::text = $1)
+                 AND ($2::bool IS NULL OR blocked = $2)
+               ORDER BY id ASC
+               LIMIT $3 OFFSET $4"#,
+            role,
+            blocked,
+            limit,
+            offset
+        )
+        .fetch_all(self)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    async fn update<F>(&self, actor_user_id: i64, actor_role: Role, id: i64, func: F) -> Result<User, Error>
     where
-        F: FnOnce(&mut Self) -> Result<bool, Error>,
+        F: FnOnce(&mut User) -> Result<bool, Error>,
     {
-        let mut tx = pool.begin().await?;
+        let mut tx = self.begin().await?;
 
-        let mut user = sqlx::query_as!(
+        let before: User = sqlx::query_as!(
             DbUser,
             r#"SELECT id, username, password_hash, role AS "role: DbRole",
-                      parent_id, created_at
-               FROM users WHERE id = $1 FOR UPDATE"#,
+                      parent_id, created_at, deleted_at, blocked
+               FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
             id
         )
         .fetch_optional(&mut *tx)
@@ -162,6 +266,7 @@ impl User {
         .ok_or(Error::UserNotFound)?
         .try_into()?;
 
+        let mut user = before.clone();
         let updated = func(&mut user)?;
 
         if !updated {
@@ -176,7 +281,7 @@ impl User {
                SET username = $1, password_hash = $2, role = $3, parent_id = $4
                WHERE id = $5
                RETURNING id, username, password_hash, role AS "role: DbRole",
-                         parent_id, created_at"#,
+                         parent_id, created_at, deleted_at, blocked"#,
             user.username,
             user.password_hash,
             user.role as DbRole,
@@ -186,36 +291,191 @@ impl User {
         .fetch_one(&mut *tx)
         .await?;
 
+        let user: User = user.try_into()?;
+
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "update",
+            "user",
+            user.id,
+            audit::diff(
+                &serde_json::to_value(&before).expect("User always serializes to JSON"),
+                &serde_json::to_value(&user).expect("User always serializes to JSON"),
+            ),
+        )
+        .await?;
+
         tx.commit().await?;
 
-        user.try_into()
+        Ok(user)
     }
 
-    pub async fn delete(pool: &PgPool, id: i64) -> Result<(), Error> {
-        let mut tx = pool.begin().await?;
+    async fn delete(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
 
         let role = sqlx::query_scalar!(
-            r#"SELECT role AS "role: DbRole" FROM users WHERE id = $1 FOR UPDATE"#,
+            r#"SELECT role AS "role: DbRole" FROM users
+               WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
             id
         )
         .fetch_one(&mut *tx)
         .await?;
 
         if role == DbRole::Admin {
-            let count = sqlx::query_scalar!("SELECT COUNT(*) FROM users WHERE role = 'admin'")
-                .fetch_one(&mut *tx)
-                .await?;
+            let count = sqlx::query_scalar!(
+                "SELECT COUNT(*) FROM users WHERE role = 'admin' AND deleted_at IS NULL"
+            )
+            .fetch_one(&mut *tx)
+            .await?;
             if count == Some(1) {
                 return Err(Error::CannotDeleteLastAdmin);
             }
         }
 
-        sqlx::query!("DELETE FROM users WHERE id = $1", id)
+        sqlx::query!("UPDATE users SET deleted_at = now() WHERE id = $1", id)
             .execute(&mut *tx)
             .await?;
 
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "delete",
+            "user",
+            id,
+            serde_json::json!({}),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn restore(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<User, Error> {
+        let mut tx = self.begin().await?;
+
+        let user: User = sqlx::query_as!(
+            DbUser,
+            r#"UPDATE users SET deleted_at = NULL
+               WHERE id = $1 AND deleted_at IS NOT NULL
+               RETURNING id, username, password_hash, role AS "role: DbRole",
+                         parent_id, created_at, deleted_at, blocked"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::UserNotFound)?
+        .try_into()?;
+
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "restore",
+            "user",
+            user.id,
+            serde_json::to_value(&user).expect("User always serializes to JSON"),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    async fn purge(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+
+        let rows_affected = sqlx::query!(
+            "DELETE FROM users WHERE id = $1 AND deleted_at IS NOT NULL",
+            id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(Error::UserNotFound);
+        }
+
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "purge",
+            "user",
+            id,
+            serde_json::json!({}),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(())
     }
 }
+
+impl User {
+    pub async fn get_by_id(store: &impl UserStore, id: i64) -> Result<Self, Error> {
+        store.get_by_id(id).await
+    }
+
+    pub async fn get_by_username(store: &impl UserStore, username: &str) -> Result<Self, Error> {
+        store.get_by_username(username).await
+    }
+
+    pub async fn list(
+        store: &impl UserStore,
+        role: Option<String>,
+        blocked: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Self>, Error> {
+        store.list(role, blocked, limit, offset).await
+    }
+
+    pub async fn update<F>(
+        store: &impl UserStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<bool, Error>,
+    {
+        store.update(actor_user_id, actor_role, id, func).await
+    }
+
+    pub async fn delete(
+        store: &impl UserStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<(), Error> {
+        store.delete(actor_user_id, actor_role, id).await
+    }
+
+    /// Un-does a previous [`User::delete`]
+    pub async fn restore(
+        store: &impl UserStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<Self, Error> {
+        store.restore(actor_user_id, actor_role, id).await
+    }
+
+    /// Permanently removes an already soft-deleted account
+    pub async fn purge(
+        store: &impl UserStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<(), Error> {
+        store.purge(actor_user_id, actor_role, id).await
+    }
+}
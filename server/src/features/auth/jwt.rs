@@ -16,6 +16,7 @@ use chrono::{
     Utc,
 };
 use jsonwebtoken::{
+    Algorithm,
     DecodingKey,
     EncodingKey,
     Header,
@@ -34,59 +35,255 @@ use crate::{
     errors::Error,
 };
 
-use super::Role;
+use super::{
+    Role,
+    User,
+    sessions::{
+        self,
+        Session,
+    },
+};
+
+/// The signing/verification strategy for every JWT this service mints —
+/// either a shared HMAC secret (the default) or a loaded asymmetric keypair,
+/// mirroring vaultwarden's configurable signing algorithm and keyfiles. An
+/// asymmetric deployment can hand the public key to a separate verifier
+/// without ever sharing something that can mint tokens.
+#[derive(Clone)]
+pub struct JwtConfig {
+    pub(crate) encoding_key: EncodingKey,
+    pub(crate) decoding_key: DecodingKey,
+    pub(crate) algorithm: Algorithm,
+}
+
+impl JwtConfig {
+    /// Reads `JWT_ALGORITHM` (`HS256` by default, or `RS256`/`EdDSA`). `HS256`
+    /// uses `JWT_SECRET`; the asymmetric algorithms instead read PEM key
+    /// material from `JWT_PRIVATE_KEY` (signing) and `JWT_PUBLIC_KEY`
+    /// (verification).
+    pub fn from_env() -> Result<Self, String> {
+        let algorithm = match std::env::var("JWT_ALGORITHM").as_deref() {
+            Ok("RS256") => Algorithm::RS256,
+            Ok("EdDSA") => Algorithm::EdDSA,
+            Ok("HS256") | Err(_) => Algorithm::HS256,
+            Ok(other) => return Err(format!("unsupported JWT_ALGORITHM: {other}")),
+        };
+
+        if algorithm == Algorithm::HS256 {
+            let secret = std::env::var("JWT_SECRET")
+                .map_err(|_| "missing JWT_SECRET environment variable".to_owned())?;
+            return Ok(Self {
+                encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+                algorithm,
+            });
+        }
+
+        let private_key = std::env::var("JWT_PRIVATE_KEY")
+            .map_err(|_| "missing JWT_PRIVATE_KEY environment variable".to_owned())?;
+        let public_key = std::env::var("JWT_PUBLIC_KEY")
+            .map_err(|_| "missing JWT_PUBLIC_KEY environment variable".to_owned())?;
+
+        let (encoding_key, decoding_key) = match algorithm {
+            Algorithm::RS256 => (
+                EncodingKey::from_rsa_pem(private_key.as_bytes()).map_err(|e| e.to_string())?,
+                DecodingKey::from_rsa_pem(public_key.as_bytes()).map_err(|e| e.to_string())?,
+            ),
+            Algorithm::EdDSA => (
+                EncodingKey::from_ed_pem(private_key.as_bytes()).map_err(|e| e.to_string())?,
+                DecodingKey::from_ed_pem(public_key.as_bytes()).map_err(|e| e.to_string())?,
+            ),
+            _ => unreachable!("HS256 already returned above"),
+        };
+
+        Ok(Self { encoding_key, decoding_key, algorithm })
+    }
+}
+
+/// Distinguishes a normal bearer token from one scoped to a single purpose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenKind {
+    Access,
+    /// Only usable against `POST /2fa/verify`, issued after a correct
+    /// password but before a TOTP/recovery code has been checked
+    TwoFactorPending,
+    /// Only usable as the confirmation header on a single account deletion,
+    /// scoped to the user id in `sub`
+    Delete,
+}
+
+/// Signs an access token bound to an existing session's `jti`, so it can be revoked
+pub fn sign(
+    sub: i64,
+    role: Role,
+    jti: &str,
+    keys: &JwtConfig,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    #[allow(clippy::expect_used)]
+    let expiration = Utc::now()
+        .checked_add_signed(sessions::ACCESS_TTL)
+        .expect("never out of date")
+        .timestamp();
+    let claims = Claims {
+        sub,
+        role,
+        kind: TokenKind::Access,
+        jti: jti.to_owned(),
+        exp: expiration.cast_unsigned(),
+    };
+
+    encode(&Header::new(keys.algorithm), &claims, &keys.encoding_key)
+}
+
+/// Signs a short-lived token that only grants access to `POST /2fa/verify`
+pub fn sign_two_factor_pending(
+    sub: i64,
+    role: Role,
+    keys: &JwtConfig,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    #[allow(clippy::expect_used)]
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::minutes(5))
+        .expect("never out of date")
+        .timestamp();
+    let claims = Claims {
+        sub,
+        role,
+        kind: TokenKind::TwoFactorPending,
+        jti: String::new(),
+        exp: expiration.cast_unsigned(),
+    };
+
+    encode(&Header::new(keys.algorithm), &claims, &keys.encoding_key)
+}
 
-pub fn sign(sub: i64, role: Role, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+/// Signs a short-lived token confirming intent to delete the account `sub`,
+/// required as a second factor alongside the normal bearer token before
+/// `DELETE /me` or `DELETE /users/{id}` takes effect
+pub fn sign_delete_token(
+    sub: i64,
+    role: Role,
+    keys: &JwtConfig,
+) -> Result<String, jsonwebtoken::errors::Error> {
     #[allow(clippy::expect_used)]
     let expiration = Utc::now()
-        .checked_add_signed(Duration::hours(24))
+        .checked_add_signed(Duration::minutes(5))
         .expect("never out of date")
         .timestamp();
     let claims = Claims {
         sub,
         role,
+        kind: TokenKind::Delete,
+        jti: String::new(),
         exp: expiration.cast_unsigned(),
     };
 
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_bytes()),
-    )
+    encode(&Header::new(keys.algorithm), &claims, &keys.encoding_key)
+}
+
+/// Checks that `token` is a [`sign_delete_token`] confirmation for `sub`
+pub fn verify_delete_token(token: &str, sub: i64, keys: &JwtConfig) -> Result<(), Error> {
+    let claims = decode_claims(token, keys)?;
+
+    if claims.kind != TokenKind::Delete || claims.sub != sub {
+        return Err(Error::InvalidToken);
+    }
+
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
 struct Claims {
     pub sub: i64,
     pub role: Role,
+    #[serde(default = "access_kind")]
+    pub kind: TokenKind,
+    /// The session id an access token is bound to; empty for non-access tokens
+    #[serde(default)]
+    pub jti: String,
     pub exp: u64,
 }
 
+fn access_kind() -> TokenKind {
+    TokenKind::Access
+}
+
 pub struct Authenticated {
     pub id: i64,
     pub role: Role,
     pub token: String,
+    /// The session id this token is bound to
+    pub jti: String,
     // pub expires: DateTime<Utc>,
 }
 
-impl TryFrom<(Bearer, &str)> for Authenticated {
-    type Error = Error;
+impl Authenticated {
+    async fn from_bearer(bearer: Bearer, state: &AppState) -> Result<Self, Error> {
+        let claims = decode_claims(bearer.token(), &state.jwt)?;
+
+        if claims.kind != TokenKind::Access {
+            return Err(Error::InvalidToken);
+        }
+
+        if state.revoked_jtis.contains(&claims.jti)
+            || !Session::is_valid(&state.pool, &claims.jti).await?
+        {
+            return Err(Error::InvalidToken);
+        }
+
+        // Re-checked on every request (not just at login) so disabling an
+        // account stops a still-unexpired JWT from working immediately
+        if User::is_blocked(&state.pool, claims.sub).await? {
+            return Err(Error::AccountBlocked);
+        }
+
+        Session::touch(&state.pool, &claims.jti).await?;
+
+        Ok(Self {
+            id: claims.sub,
+            role: claims.role,
+            token: bearer.token().to_owned(),
+            jti: claims.jti,
+        })
+    }
+}
 
-    fn try_from((bearer, secret): (Bearer, &str)) -> Result<Self, Self::Error> {
-        let token_data = decode::<Claims>(
-            bearer.token(),
-            &DecodingKey::from_secret(secret.as_bytes()),
-            &Validation::default(),
-        )
+fn decode_claims(token: &str, keys: &JwtConfig) -> Result<Claims, Error> {
+    decode::<Claims>(token, &keys.decoding_key, &Validation::new(keys.algorithm))
+        .map(|data| data.claims)
         .map_err(|e| match e.kind() {
             ErrorKind::ExpiredSignature => Error::TokenExpired,
             _ => Error::InvalidToken,
-        })?;
+        })
+}
+
+/// A token minted by [`sign_two_factor_pending`], accepted only by `POST /2fa/verify`
+pub struct TwoFactorPending {
+    pub id: i64,
+    pub role: Role,
+}
+
+impl FromRequestParts<AppState> for TwoFactorPending {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await?;
+
+        let claims = decode_claims(bearer.token(), &state.jwt)?;
+
+        if claims.kind != TokenKind::TwoFactorPending {
+            return Err(Error::InvalidToken);
+        }
 
         Ok(Self {
-            id: token_data.claims.sub,
-            role: token_data.claims.role,
-            token: bearer.token().to_owned(),
+            id: claims.sub,
+            role: claims.role,
         })
     }
 }
@@ -102,7 +299,27 @@ impl FromRequestParts<AppState> for Authenticated {
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await?;
 
-        Self::try_from((bearer, state.jwt_secret.as_str()))
+        Self::from_bearer(bearer, state).await
+    }
+}
+
+/// Wraps [`Authenticated`], rejecting anything but [`Role::Admin`]
+pub struct AdminAuthenticated(pub Authenticated);
+
+impl FromRequestParts<AppState> for AdminAuthenticated {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let auth = Authenticated::from_request_parts(parts, state).await?;
+
+        if auth.role != Role::Admin {
+            return Err(Error::AdminOnly);
+        }
+
+        Ok(Self(auth))
     }
 }
 
@@ -123,6 +340,6 @@ impl FromRequestParts<AppState> for MaybeAuthenticated {
             },
         };
 
-        Authenticated::try_from((bearer, state.jwt_secret.as_str())).map(|x| Self(Some(x)))
+        Authenticated::from_bearer(bearer, state).await.map(|x| Self(Some(x)))
     }
 }
@@ -13,17 +13,39 @@ use crate::AppState;
 
 mod db;
 mod jwt;
+mod oauth;
+mod sessions;
 mod routes {
+    pub mod auth_requests;
     pub mod login;
     pub mod me;
     pub mod register;
+    pub mod sessions;
 }
 
 pub use db::{
     Role,
     User,
 };
-pub use jwt::Authenticated;
+pub use jwt::{
+    AdminAuthenticated,
+    Authenticated,
+    JwtConfig,
+    TwoFactorPending,
+    sign,
+    sign_delete_token,
+    sign_two_factor_pending,
+    verify_delete_token,
+};
+pub use oauth::OAuthConfig;
+pub use routes::login::LoginOutcome;
+pub use routes::me::DeleteTokenResponse;
+pub use sessions::{
+    RevocationCache,
+    Session,
+    client_ip,
+    device_info,
+};
 
 pub const TAG: &str = "Authentication";
 
@@ -31,11 +53,22 @@ pub fn router() -> OpenApiRouter<AppState> {
     OpenApiRouter::new()
         .routes(routes!(routes::register::register))
         .routes(routes!(routes::login::login))
+        .nest("/oauth", oauth::router())
         .routes(routes!(
             routes::me::get,
             routes::me::patch,
             routes::me::delete
         ))
+        .routes(routes!(routes::me::request_delete_token))
+        .routes(routes!(routes::sessions::refresh))
+        .routes(routes!(routes::sessions::list, routes::sessions::revoke_all))
+        .routes(routes!(routes::sessions::revoke))
+        .routes(routes!(routes::sessions::logout))
+        .routes(routes!(routes::auth_requests::list))
+        .routes(routes!(
+            routes::auth_requests::approve,
+            routes::auth_requests::deny
+        ))
 }
 
 #[derive(FromRow, Serialize, ToSchema, IntoResponses)]
@@ -44,4 +77,16 @@ pub struct AuthResponse {
     #[serde(flatten)]
     user: db::User,
     token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+}
+
+impl AuthResponse {
+    pub fn new(user: db::User, token: String, refresh_token: Option<String>) -> Self {
+        Self {
+            user,
+            token,
+            refresh_token,
+        }
+    }
 }
@@ -0,0 +1,370 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+};
+
+use axum::{
+    Json,
+    extract::{
+        ConnectInfo,
+        Path,
+        Query,
+        State,
+    },
+    http::HeaderMap,
+    response::{
+        IntoResponse,
+        Redirect,
+        Response,
+    },
+};
+use chrono::{
+    Duration,
+    Utc,
+};
+use jsonwebtoken::{
+    Header,
+    Validation,
+    decode,
+    encode,
+};
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sqlx::PgPool;
+use utoipa::IntoParams;
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    responses::{
+        OAuthAuthorize,
+        OAuthCallback,
+    },
+};
+
+use super::{
+    AuthResponse,
+    TAG,
+    db::{
+        Role,
+        User,
+    },
+    jwt::{
+        JwtConfig,
+        sign,
+    },
+    sessions::{
+        Session,
+        client_ip,
+        device_info,
+    },
+};
+
+/// One external identity provider's client registration, read from env at startup
+#[derive(Clone)]
+pub struct OAuthProvider {
+    pub client_id: String,
+    pub client_secret: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    /// Where the provider redirects back to after the user authorizes
+    pub redirect_uri: String,
+}
+
+/// All configured providers, keyed by the name used in `/auth/oauth/{provider}/...`
+#[derive(Clone, Default)]
+pub struct OAuthConfig(HashMap<String, OAuthProvider>);
+
+impl OAuthConfig {
+    /// Reads `OAUTH_PROVIDERS` (a comma-separated list of names) and, for each
+    /// `NAME`, `OAUTH_{NAME}_CLIENT_ID`/`_CLIENT_SECRET`/`_AUTH_URL`/`_TOKEN_URL`/
+    /// `_USERINFO_URL`/`_REDIRECT_URI`. Absent entirely (no `OAUTH_PROVIDERS`) means
+    /// no providers are configured, rather than a startup error.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(names) = std::env::var("OAUTH_PROVIDERS") else {
+            return Ok(Self::default());
+        };
+
+        let mut providers = HashMap::new();
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let prefix = format!("OAUTH_{}", name.to_uppercase());
+            let var = |suffix: &str| -> Result<String, String> {
+                std::env::var(format!("{prefix}_{suffix}"))
+                    .map_err(|_| format!("missing {prefix}_{suffix}"))
+            };
+
+            providers.insert(
+                name.to_owned(),
+                OAuthProvider {
+                    client_id: var("CLIENT_ID")?,
+                    client_secret: var("CLIENT_SECRET")?,
+                    auth_url: var("AUTH_URL")?,
+                    token_url: var("TOKEN_URL")?,
+                    userinfo_url: var("USERINFO_URL")?,
+                    redirect_uri: var("REDIRECT_URI")?,
+                },
+            );
+        }
+
+        Ok(Self(providers))
+    }
+
+    fn get<'a>(&'a self, provider: &str) -> Result<&'a OAuthProvider, Error> {
+        self.0.get(provider).ok_or(Error::OAuthProviderNotFound)
+    }
+}
+
+/// Claims of the short-lived, signed `state` parameter round-tripped through
+/// the provider, standing in for server-side CSRF-state storage
+#[derive(Serialize, Deserialize)]
+struct StateClaims {
+    provider: String,
+    exp: u64,
+}
+
+fn sign_state(provider: &str, keys: &JwtConfig) -> Result<String, Error> {
+    #[allow(clippy::expect_used)]
+    let exp = Utc::now()
+        .checked_add_signed(Duration::minutes(10))
+        .expect("never out of date")
+        .timestamp()
+        .cast_unsigned();
+
+    Ok(encode(
+        &Header::new(keys.algorithm),
+        &StateClaims {
+            provider: provider.to_owned(),
+            exp,
+        },
+        &keys.encoding_key,
+    )?)
+}
+
+fn verify_state(state: &str, provider: &str, keys: &JwtConfig) -> Result<(), Error> {
+    let claims = decode::<StateClaims>(state, &keys.decoding_key, &Validation::new(keys.algorithm))
+        .map_err(|_| Error::OAuthStateMismatch)?
+        .claims;
+
+    if claims.provider != provider {
+        return Err(Error::OAuthStateMismatch);
+    }
+
+    Ok(())
+}
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(authorize))
+        .routes(routes!(callback))
+}
+
+/// Start an OAuth2 login
+///
+/// Redirects to the provider's authorization endpoint with a signed,
+/// short-lived `state` parameter that `callback` verifies on return.
+#[utoipa::path(
+    get,
+    path = "/{provider}/authorize",
+    tag = TAG,
+    responses(OAuthAuthorize)
+)]
+pub async fn authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<Response, Error> {
+    let config = state.oauth.get(&provider)?;
+    let csrf_state = sign_state(&provider, &state.jwt)?;
+
+    let url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&state={}",
+        config.auth_url,
+        urlencoding::encode(&config.client_id),
+        urlencoding::encode(&config.redirect_uri),
+        urlencoding::encode(&csrf_state),
+    );
+
+    Ok(Redirect::temporary(&url).into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CallbackQuery {
+    code: String,
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+    /// The provider's stable subject identifier for this account
+    #[serde(alias = "id")]
+    sub: String,
+    #[serde(default)]
+    email: Option<String>,
+    #[serde(default)]
+    login: Option<String>,
+}
+
+/// Complete an OAuth2 login
+///
+/// Exchanges the authorization code, fetches the provider's userinfo, and
+/// upserts a local account linked to it via `oauth_identities`. Mints the
+/// same [`AuthResponse`] a password login would.
+#[utoipa::path(
+    get,
+    path = "/{provider}/callback",
+    params(CallbackQuery),
+    tag = TAG,
+    responses(OAuthCallback)
+)]
+pub async fn callback(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(provider): Path<String>,
+    Query(query): Query<CallbackQuery>,
+) -> Result<Json<AuthResponse>, Error> {
+    let config = state.oauth.get(&provider)?.clone();
+    verify_state(&query.state, &provider, &state.jwt)?;
+
+    let client = reqwest::Client::new();
+
+    let token: TokenResponse = client
+        .post(&config.token_url)
+        .form(&[
+            ("grant_type", "authorization_code"),
+            ("code", query.code.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?
+        .error_for_status()
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?;
+
+    let userinfo: UserInfo = client
+        .get(&config.userinfo_url)
+        .bearer_auth(token.access_token)
+        .send()
+        .await
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?
+        .error_for_status()
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?
+        .json()
+        .await
+        .map_err(|e| Error::OAuthExchangeFailed(e.to_string()))?;
+
+    let user = upsert_identity(&state.pool, &provider, &userinfo).await?;
+
+    if user.blocked {
+        return Err(Error::AccountBlocked);
+    }
+
+    let session = Session::create(
+        &state.pool,
+        user.id,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
+
+    Ok(Json(AuthResponse::new(
+        user,
+        token,
+        Some(session.refresh_token),
+    )))
+}
+
+/// Finds the local account already linked to this `(provider, subject)` pair,
+/// or provisions a fresh `owner` account and links it on first login
+async fn upsert_identity(pool: &PgPool, provider: &str, userinfo: &UserInfo) -> Result<User, Error> {
+    let existing = sqlx::query_scalar!(
+        "SELECT user_id FROM oauth_identities WHERE provider = $1 AND subject = $2",
+        provider,
+        userinfo.sub
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(user_id) = existing {
+        return User::get_by_id(pool, user_id).await;
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let username = unique_username(
+        &mut tx,
+        userinfo.login.as_deref().or(userinfo.email.as_deref()).unwrap_or(&userinfo.sub),
+    )
+    .await?;
+
+    // Federated accounts have no local password: the stored value is random
+    // text rather than an argon2 hash, so a password login for it fails
+    let unusable_password_hash = generate_code(32);
+    let user = User::create(&mut *tx, &username, &unusable_password_hash, Role::Owner).await?;
+
+    sqlx::query!(
+        "INSERT INTO oauth_identities (user_id, provider, subject) VALUES ($1, $2, $3)",
+        user.id,
+        provider,
+        userinfo.sub
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(user)
+}
+
+/// Finds a free username derived from `hint`, appending a numeric suffix on collision
+async fn unique_username(conn: &mut sqlx::PgConnection, hint: &str) -> Result<String, Error> {
+    let base: String = hint.chars().filter(char::is_ascii_alphanumeric).collect();
+    let base = if base.is_empty() { "user".to_owned() } else { base };
+
+    let mut candidate = base.clone();
+    let mut suffix = 0u32;
+    loop {
+        let taken = sqlx::query_scalar!(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1) AS \"exists!\"",
+            candidate
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        if !taken {
+            return Ok(candidate);
+        }
+
+        suffix += 1;
+        candidate = format!("{base}{suffix}");
+    }
+}
+
+fn generate_code(len: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
@@ -0,0 +1,107 @@
+//! Owner-facing control over their Users' pending `auth_requests`, reusing
+//! the existing self-service auth-request table. Logging in still goes
+//! through `routes::login` directly for every role; turning a `Role::User`
+//! login into one of these pending rows (rather than a minted token) is a
+//! separate change to that flow and isn't made here.
+
+use axum::{
+    Json,
+    extract::{
+        Path,
+        State,
+    },
+    http::StatusCode,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    features::{
+        auth::{
+            Authenticated,
+            Role,
+            TAG,
+            User,
+        },
+        auth_requests::{
+            AuthRequest,
+            PendingAuthRequest,
+        },
+    },
+    responses::{
+        ListAuthRequests,
+        RespondAuthRequest,
+    },
+};
+
+async fn owned_children(state: &AppState, auth: &Authenticated) -> Result<Vec<i64>, Error> {
+    match auth.role {
+        Role::Admin | Role::Owner => User::get_children(&state.pool, auth.id).await,
+        Role::User(_) => Err(Error::UsersCannotCreateUsers),
+    }
+}
+
+/// List pending auth requests raised by the caller's Users
+#[utoipa::path(
+    get,
+    path = "/me/auth-requests",
+    responses(ListAuthRequests),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    auth: Authenticated,
+) -> Result<Json<Vec<PendingAuthRequest>>, Error> {
+    let children = owned_children(&state, &auth).await?;
+    let requests = AuthRequest::list_pending_for(&state.pool, &children).await?;
+
+    Ok(Json(
+        requests
+            .into_iter()
+            .map(|r| PendingAuthRequest {
+                id: r.id,
+                requesting_ip: r.requesting_ip,
+                created_at: r.created_at,
+            })
+            .collect(),
+    ))
+}
+
+/// Approve one of the caller's Users' pending login attempts
+#[utoipa::path(
+    post,
+    path = "/me/auth-requests/{id}/approve",
+    responses(RespondAuthRequest),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn approve(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    let children = owned_children(&state, &auth).await?;
+    AuthRequest::respond(&state.pool, id, &children, true).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deny one of the caller's Users' pending login attempts
+#[utoipa::path(
+    post,
+    path = "/me/auth-requests/{id}/deny",
+    responses(RespondAuthRequest),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn deny(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    let children = owned_children(&state, &auth).await?;
+    AuthRequest::respond(&state.pool, id, &children, false).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,27 +1,39 @@
-use argon2::{
-    Argon2,
-    password_hash::{
-        PasswordHash,
-        PasswordVerifier,
-    },
-};
 use axum::{
     Json,
-    extract::State,
+    extract::{
+        ConnectInfo,
+        State,
+    },
+    http::HeaderMap,
 };
 use garde::Validate;
-use serde::Deserialize;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::net::SocketAddr;
 use utoipa::ToSchema;
 
 use crate::{
     AppState,
     errors::Error,
     extractors::Validated,
-    features::auth::{
-        AuthResponse,
-        TAG,
-        db::User,
-        jwt::sign,
+    features::{
+        auth::{
+            AuthResponse,
+            TAG,
+            db::User,
+            jwt::{
+                sign,
+                sign_two_factor_pending,
+            },
+            sessions::{
+                Session,
+                client_ip,
+                device_info,
+            },
+        },
+        two_factor,
     },
     responses::Login,
 };
@@ -37,7 +49,22 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Serialize, ToSchema)]
+#[serde(untagged)]
+pub enum LoginOutcome {
+    Authenticated(AuthResponse),
+    /// Credentials were correct, but a TOTP/recovery code is required
+    TwoFactorRequired {
+        two_factor_required: bool,
+        pending_token: String,
+    },
+}
+
 /// Log into an existing user
+///
+/// If the user has two-factor authentication enabled, this returns a
+/// short-lived pending token instead of an access token; complete the
+/// login via `POST /2fa/verify`.
 #[utoipa::path(
     post,
     path = "/login",
@@ -47,16 +74,44 @@ pub struct LoginRequest {
 )]
 pub async fn login(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Validated(payload): Validated<LoginRequest>,
-) -> Result<Json<AuthResponse>, Error> {
-    let user = User::get_by_username(&state.pool, &payload.username).await?;
+) -> Result<Json<LoginOutcome>, Error> {
+    let mut user = User::get_by_username(&state.pool, &payload.username).await?;
+
+    if let Some(new_hash) = state.argon2.verify(&payload.password, &user.password_hash)? {
+        user = User::update(&state.pool, user.id, user.role, user.id, |u| {
+            u.password_hash = new_hash.clone();
+            Ok(true)
+        })
+        .await?;
+    }
+
+    if user.blocked {
+        return Err(Error::AccountBlocked);
+    }
 
-    Argon2::default().verify_password(
-        payload.password.as_bytes(),
-        &PasswordHash::new(&user.password_hash)?,
-    )?;
+    if two_factor::is_enabled(&state.pool, user.id).await? {
+        let pending_token = sign_two_factor_pending(user.id, user.role, &state.jwt)?;
+        return Ok(Json(LoginOutcome::TwoFactorRequired {
+            two_factor_required: true,
+            pending_token,
+        }));
+    }
 
-    let token = sign(user.id, user.role, &state.jwt_secret)?;
+    let session = Session::create(
+        &state.pool,
+        user.id,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
 
-    Ok(Json(AuthResponse { user, token }))
+    Ok(Json(LoginOutcome::Authenticated(AuthResponse::new(
+        user,
+        token,
+        Some(session.refresh_token),
+    ))))
 }
@@ -1,20 +1,16 @@
-use argon2::{
-    Argon2,
-    PasswordHash,
-    PasswordVerifier,
-    password_hash::{
-        PasswordHasher,
-        SaltString,
-        rand_core::OsRng,
-    },
-};
 use axum::{
     Json,
     extract::State,
-    http::StatusCode,
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
 };
 use garde::Validate;
-use serde::Deserialize;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 use utoipa::ToSchema;
 
 use crate::{
@@ -29,11 +25,14 @@ use crate::{
             User,
         },
         jwt::Authenticated,
+        sign_delete_token,
+        verify_delete_token,
     },
     responses::{
         DeleteMe,
         GetMe,
         PatchMe,
+        RequestAccountDeletion,
     },
 };
 
@@ -52,6 +51,7 @@ pub async fn get(
     Ok(Json(AuthResponse {
         user: User::get_by_id(&state.pool, auth.id).await?,
         token: auth.token,
+        refresh_token: None,
     }))
 }
 
@@ -84,26 +84,23 @@ pub async fn patch(
     Validated(payload): Validated<ChangeRequest>,
 ) -> Result<Json<AuthResponse>, Error> {
     let token = auth.token;
-    let new_password_hash = match &payload.new_password {
-        Some(pass) => Some(
-            Argon2::default()
-                .hash_password(pass.as_bytes(), &SaltString::generate(&mut OsRng))?
-                .to_string(),
-        ),
-        None => None,
-    };
+    let new_password_hash = payload
+        .new_password
+        .as_deref()
+        .map(|pass| state.argon2.hash(pass))
+        .transpose()?;
 
     let payload = payload.into_inner();
-    let user = User::update(&state.pool, auth.id, |user| {
-        // FIXME: performance hit, hashing inside a transaction
-        Argon2::default().verify_password(
-            payload.password.as_bytes(),
-            &PasswordHash::new(&user.password_hash)?,
-        )?;
-
+    let user = User::update(&state.pool, auth.id, auth.role, auth.id, |user| {
         #[allow(clippy::useless_let_if_seq)]
         let mut updated = false;
 
+        // FIXME: performance hit, hashing inside a transaction
+        if let Some(rehashed) = state.argon2.verify(&payload.password, &user.password_hash)? {
+            user.password_hash = rehashed;
+            updated = true;
+        }
+
         if let Some(name) = payload.new_username
             && name != user.username
         {
@@ -121,7 +118,11 @@ pub async fn patch(
     })
     .await?;
 
-    Ok(Json(AuthResponse { user, token }))
+    Ok(Json(AuthResponse {
+        user,
+        token,
+        refresh_token: None,
+    }))
 }
 
 #[derive(Deserialize, Validate, ToSchema)]
@@ -131,7 +132,43 @@ pub struct DeleteRequest {
     pub password: String,
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct DeleteTokenResponse {
+    pub delete_token: String,
+}
+
+/// Request a deletion confirmation token
+///
+/// Confirms the caller's password and mints a 5-minute token scoped to
+/// their own account, which `DELETE /me` then requires via the
+/// `x-delete-token` header. Splitting this from the delete itself means a
+/// CSRF'd or accidental `DELETE /me` can't destroy the account on its own.
+#[utoipa::path(
+    post,
+    path = "/me/delete-token",
+    request_body = DeleteRequest,
+    responses(RequestAccountDeletion),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn request_delete_token(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Validated(payload): Validated<DeleteRequest>,
+) -> Result<Json<DeleteTokenResponse>, Error> {
+    let user = User::get_by_id(&state.pool, auth.id).await?;
+
+    state.argon2.verify(&payload.password, &user.password_hash)?;
+
+    let delete_token = sign_delete_token(user.id, user.role, &state.jwt)?;
+
+    Ok(Json(DeleteTokenResponse { delete_token }))
+}
+
 /// Delete current user
+///
+/// Requires a deletion token from [`request_delete_token`] in the
+/// `x-delete-token` header, scoped to the caller's own id.
 #[utoipa::path(
     delete,
     path = "/me",
@@ -143,20 +180,25 @@ pub struct DeleteRequest {
 pub async fn delete(
     State(state): State<AppState>,
     auth: Authenticated,
+    headers: HeaderMap,
     Validated(payload): Validated<DeleteRequest>,
 ) -> Result<StatusCode, Error> {
     if auth.role == Role::Admin {
         return Err(Error::CannotDeleteAnAdmin);
     }
 
+    let delete_token = headers
+        .get("x-delete-token")
+        .ok_or(Error::MissingCredentials)?
+        .to_str()
+        .map_err(|_| Error::InvalidToken)?;
+    verify_delete_token(delete_token, auth.id, &state.jwt)?;
+
     let user = User::get_by_id(&state.pool, auth.id).await?;
 
-    Argon2::default().verify_password(
-        payload.password.as_bytes(),
-        &PasswordHash::new(&user.password_hash)?,
-    )?;
+    state.argon2.verify(&payload.password, &user.password_hash)?;
 
-    User::delete(&state.pool, user.id).await?;
+    User::delete(&state.pool, auth.id, auth.role, user.id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
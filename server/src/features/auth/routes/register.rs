@@ -1,18 +1,17 @@
-use argon2::{
-    Argon2,
-    password_hash::{
-        PasswordHasher,
-        SaltString,
-        rand_core::OsRng,
-    },
-};
 use axum::{
     Json,
-    extract::State,
-    http::StatusCode,
+    extract::{
+        ConnectInfo,
+        State,
+    },
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
 };
 use garde::Validate;
 use serde::Deserialize;
+use std::net::SocketAddr;
 use utoipa::ToSchema;
 
 use crate::{
@@ -20,17 +19,25 @@ use crate::{
     errors::Error,
     extractors::Validated,
 
-    features::auth::{
-        AuthResponse,
-        TAG,
-        db::{
-            Role,
-            User,
-        },
-        jwt::{
-            MaybeAuthenticated,
-            sign,
+    features::{
+        auth::{
+            AuthResponse,
+            TAG,
+            db::{
+                Role,
+                User,
+            },
+            jwt::{
+                MaybeAuthenticated,
+                sign,
+            },
+            sessions::{
+                Session,
+                client_ip,
+                device_info,
+            },
         },
+        invites::Invite,
     },
     responses::Register,
 };
@@ -44,12 +51,17 @@ pub struct RegisterRequest {
     #[garde(length(chars, min = 8))]
     #[schema(min_length = 8, example = "lumirum!")]
     pub password: String,
+    /// Invite code granting a specific role; required when open registration is disabled
+    #[garde(length(chars, min = 1))]
+    #[schema(example = "aB3xQ9mK2p")]
+    pub invite_code: Option<String>,
 }
 
 /// Register a new account
 ///
-/// If an optional JWT is provided for authentication,
-/// a user with a role one step down will be created.
+/// With an `invite_code`, the new account's role comes from the invite.
+/// Without one, falls back to open registration (if enabled): an optional
+/// JWT derives a role one step down, or an `owner` account is created.
 #[utoipa::path(
     post,
     path = "/register",
@@ -60,27 +72,51 @@ pub struct RegisterRequest {
 )]
 pub async fn register(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     MaybeAuthenticated(auth): MaybeAuthenticated,
     Validated(payload): Validated<RegisterRequest>,
 ) -> Result<(StatusCode, Json<AuthResponse>), Error> {
-    let role = match auth {
-        None => Role::Owner,
-        Some(auth) => match auth.role {
-            Role::Admin => Role::Owner,
-            Role::Owner => Role::User(auth.id),
-            Role::User(_) => return Err(Error::UsersCannotCreateUsers),
-        },
+    let password_hash = state.argon2.hash(&payload.password)?;
+
+    let mut tx = state.pool.begin().await?;
+
+    let role = match &payload.invite_code {
+        Some(code) => Invite::redeem(&mut *tx, code).await?.role(),
+        None => {
+            if !state.open_registration {
+                return Err(Error::InviteRequired);
+            }
+            match auth {
+                None => Role::Owner,
+                Some(auth) => match auth.role {
+                    Role::Admin => Role::Owner,
+                    Role::Owner => Role::User(auth.id),
+                    Role::User(_) => return Err(Error::UsersCannotCreateUsers),
+                },
+            }
+        }
     };
 
-    let password_hash = Argon2::default()
-        .hash_password(
-            payload.password.as_bytes(),
-            &SaltString::generate(&mut OsRng),
-        )?
-        .to_string();
+    let user = User::create(&mut *tx, &payload.username, &password_hash, role).await?;
+
+    tx.commit().await?;
 
-    let user = User::create(&state.pool, &payload.username, &password_hash, role).await?;
-    let token = sign(user.id, user.role, &state.jwt_secret)?;
+    let session = Session::create(
+        &state.pool,
+        user.id,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
 
-    Ok((StatusCode::CREATED, Json(AuthResponse { user, token })))
+    Ok((
+        StatusCode::CREATED,
+        Json(AuthResponse {
+            user,
+            token,
+            refresh_token: Some(session.refresh_token),
+        }),
+    ))
 }
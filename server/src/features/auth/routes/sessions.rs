@@ -0,0 +1,154 @@
+use axum::{
+    Json,
+    extract::{
+        ConnectInfo,
+        Path,
+        State,
+    },
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
+};
+use garde::Validate;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    errors::Error,
+    extractors::Validated,
+    features::auth::{
+        AuthResponse,
+        Authenticated,
+        TAG,
+        User,
+        jwt::sign,
+        sessions::{
+            Session,
+            client_ip,
+            device_info,
+        },
+    },
+    responses::{
+        ListSessions,
+        Logout,
+        Refresh,
+        RevokeAllSessions,
+        RevokeSession,
+    },
+};
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct RefreshRequest {
+    #[garde(length(chars, min = 1))]
+    pub refresh_token: String,
+}
+
+/// Exchange a refresh token for a new access/refresh token pair
+///
+/// Rotates the refresh token: the old one stops working as soon as a new
+/// pair is issued.
+#[utoipa::path(
+    post,
+    path = "/me/refresh",
+    request_body = RefreshRequest,
+    responses(Refresh),
+    tag = TAG
+)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Validated(payload): Validated<RefreshRequest>,
+) -> Result<Json<AuthResponse>, Error> {
+    let old = Session::get_by_refresh_token(&state.pool, &payload.refresh_token).await?;
+    let session = Session::rotate(
+        &state.pool,
+        &payload.refresh_token,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+
+    let user = User::get_by_id(&state.pool, old.user_id).await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
+
+    Ok(Json(AuthResponse::new(user, token, Some(session.refresh_token))))
+}
+
+/// List the authenticated user's active sessions
+#[utoipa::path(
+    get,
+    path = "/me/sessions",
+    responses(ListSessions),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn list(
+    State(state): State<AppState>,
+    auth: Authenticated,
+) -> Result<Json<Vec<Session>>, Error> {
+    Ok(Json(Session::list_active(&state.pool, auth.id).await?))
+}
+
+/// Revoke a single session
+#[utoipa::path(
+    delete,
+    path = "/me/sessions/{jti}",
+    responses(RevokeSession),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn revoke(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(jti): Path<String>,
+) -> Result<StatusCode, Error> {
+    if !Session::revoke(&state.pool, &jti, auth.id).await? {
+        return Err(Error::SessionNotFound);
+    }
+    state.revoked_jtis.insert(jti);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revoke every session belonging to the authenticated user (logout-everywhere)
+#[utoipa::path(
+    delete,
+    path = "/me/sessions",
+    responses(RevokeAllSessions),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn revoke_all(
+    State(state): State<AppState>,
+    auth: Authenticated,
+) -> Result<StatusCode, Error> {
+    Session::revoke_all(&state.pool, auth.id).await?;
+    state.revoked_jtis.insert(auth.jti);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Log out of the current session
+///
+/// Convenience wrapper around revoking the session the caller is currently
+/// authenticated with, without needing to know its own `jti`.
+#[utoipa::path(
+    post,
+    path = "/logout",
+    responses(Logout),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    auth: Authenticated,
+) -> Result<StatusCode, Error> {
+    Session::revoke(&state.pool, &auth.jti, auth.id).await?;
+    state.revoked_jtis.insert(auth.jti);
+
+    Ok(StatusCode::NO_CONTENT)
+}
\ No newline at end of file
@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        RwLock,
+    },
+};
+
+use axum::http::{
+    HeaderMap,
+    header,
+};
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use serde::Serialize;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+
+/// How long a refresh token (and its session) stays valid for
+const REFRESH_TTL: Duration = Duration::days(30);
+/// How long an access token minted from a session stays valid for
+pub const ACCESS_TTL: Duration = Duration::minutes(15);
+
+// Deliberately SHA-256 rather than the Argon2 hasher used for passwords:
+// refresh tokens are high-entropy random secrets, not low-entropy user input,
+// so a slow KDF buys nothing here, and Argon2's per-call random salt would
+// make `refresh_token_hash = $1` lookups impossible without scanning every row.
+fn hash_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    format!("{digest:x}")
+}
+
+fn generate_token(len: usize) -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Reads the `User-Agent` header as a best-effort device description
+pub fn device_info(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+}
+
+pub fn client_ip(addr: SocketAddr) -> String {
+    addr.ip().to_string()
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Session {
+    pub jti: String,
+    #[serde(skip)]
+    pub user_id: i64,
+    #[serde(skip)]
+    pub refresh_token_hash: String,
+    pub device_info: Option<String>,
+    pub ip: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked: bool,
+}
+
+/// An access/refresh token pair backed by a new session
+pub struct IssuedSession {
+    pub jti: String,
+    pub refresh_token: String,
+}
+
+impl Session {
+    /// Starts a new session, returning its jti and the plaintext refresh token
+    pub async fn create(
+        pool: &PgPool,
+        user_id: i64,
+        device_info: Option<String>,
+        ip: Option<String>,
+    ) -> Result<IssuedSession, Error> {
+        let jti = generate_token(32);
+        let refresh_token = generate_token(48);
+
+        sqlx::query!(
+            "INSERT INTO sessions (jti, user_id, refresh_token_hash, device_info, ip, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            jti,
+            user_id,
+            hash_token(&refresh_token),
+            device_info,
+            ip,
+            Utc::now() + REFRESH_TTL
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(IssuedSession { jti, refresh_token })
+    }
+
+    /// Looks up a session by its plaintext refresh token
+    pub async fn get_by_refresh_token(pool: &PgPool, token: &str) -> Result<Self, Error> {
+        sqlx::query_as!(
+            Self,
+            "SELECT * FROM sessions WHERE refresh_token_hash = $1",
+            hash_token(token)
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(Error::RefreshTokenInvalid)
+    }
+
+    /// Rotates a session: invalidates its refresh token and issues a new pair,
+    /// keeping the same `jti` revoked and minting a fresh one
+    pub async fn rotate(
+        pool: &PgPool,
+        token: &str,
+        device_info: Option<String>,
+        ip: Option<String>,
+    ) -> Result<IssuedSession, Error> {
+        let mut tx = pool.begin().await?;
+
+        let session = sqlx::query_as!(
+            Self,
+            "SELECT * FROM sessions WHERE refresh_token_hash = $1 FOR UPDATE",
+            hash_token(token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::RefreshTokenInvalid)?;
+
+        if session.revoked {
+            // The token was already rotated away (or explicitly revoked) yet
+            // is being presented again: someone else may have a copy of it,
+            // so treat this as a theft signal and burn every session.
+            Self::revoke_all(&mut *tx, session.user_id).await?;
+            tx.commit().await?;
+            return Err(Error::RefreshTokenReused);
+        }
+
+        if session.expires_at < Utc::now() {
+            return Err(Error::RefreshTokenInvalid);
+        }
+
+        sqlx::query!(
+            "UPDATE sessions SET revoked = true WHERE jti = $1",
+            session.jti
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let jti = generate_token(32);
+        let refresh_token = generate_token(48);
+
+        sqlx::query!(
+            "INSERT INTO sessions (jti, user_id, refresh_token_hash, device_info, ip, expires_at)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+            jti,
+            session.user_id,
+            hash_token(&refresh_token),
+            device_info,
+            ip,
+            Utc::now() + REFRESH_TTL
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(IssuedSession { jti, refresh_token })
+    }
+
+    /// Stamps `last_used_at` on an access token's session
+    pub async fn touch(pool: &PgPool, jti: &str) -> Result<(), Error> {
+        sqlx::query!(
+            "UPDATE sessions SET last_used_at = now() WHERE jti = $1",
+            jti
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the session backing this `jti` is still usable
+    pub async fn is_valid(pool: &PgPool, jti: &str) -> Result<bool, Error> {
+        let session = sqlx::query_as!(Self, "SELECT * FROM sessions WHERE jti = $1", jti)
+            .fetch_optional(pool)
+            .await?;
+
+        Ok(match session {
+            Some(session) => !session.revoked && session.expires_at > Utc::now(),
+            None => false,
+        })
+    }
+
+    pub async fn list_active(pool: &PgPool, user_id: i64) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM sessions WHERE user_id = $1 AND revoked = false AND expires_at > now()
+             ORDER BY last_used_at DESC",
+            user_id
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /// Revokes a single session owned by `user_id`, returning whether it existed
+    pub async fn revoke(pool: &PgPool, jti: &str, user_id: i64) -> Result<bool, Error> {
+        let result = sqlx::query!(
+            "UPDATE sessions SET revoked = true WHERE jti = $1 AND user_id = $2",
+            jti,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes every session belonging to `user_id`
+    pub async fn revoke_all(executor: impl sqlx::PgExecutor<'_>, user_id: i64) -> Result<u64, Error> {
+        let result = sqlx::query!(
+            "UPDATE sessions SET revoked = true WHERE user_id = $1 AND revoked = false",
+            user_id
+        )
+        .execute(executor)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Short-lived cache of revoked jtis, checked before falling back to the
+/// session table so a replayed revoked token doesn't cost a DB round-trip
+#[derive(Clone, Default)]
+pub struct RevocationCache(Arc<RwLock<HashMap<String, DateTime<Utc>>>>);
+
+impl RevocationCache {
+    pub fn contains(&self, jti: &str) -> bool {
+        self.0.read().is_ok_and(|cache| cache.contains_key(jti))
+    }
+
+    /// Remembers `jti` as revoked until its access token would have expired anyway
+    pub fn insert(&self, jti: String) {
+        let expires_at = Utc::now() + ACCESS_TTL;
+        if let Ok(mut cache) = self.0.write() {
+            cache.retain(|_, exp| *exp > Utc::now());
+            cache.insert(jti, expires_at);
+        }
+    }
+}
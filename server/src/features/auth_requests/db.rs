@@ -0,0 +1,166 @@
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use sqlx::PgPool;
+
+use crate::errors::Error;
+
+/// How long a pending request stays valid for
+const TTL: Duration = Duration::minutes(15);
+
+pub struct AuthRequest {
+    pub id: i64,
+    pub user_id: i64,
+    pub requesting_ip: String,
+    pub access_code: String,
+    pub approved: Option<bool>,
+    pub approving_device_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// Generates a random 8-character alphanumeric access code
+fn generate_access_code() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(8)
+        .map(char::from)
+        .collect()
+}
+
+/// Constant-time byte comparison, to avoid leaking the access code via timing
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl AuthRequest {
+    pub async fn create(
+        pool: &PgPool,
+        user_id: i64,
+        requesting_ip: &str,
+    ) -> Result<Self, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            "INSERT INTO auth_requests (user_id, requesting_ip, access_code)
+             VALUES ($1, $2, $3) RETURNING *",
+            user_id,
+            requesting_ip,
+            generate_access_code()
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Self, Error> {
+        sqlx::query_as!(Self, "SELECT * FROM auth_requests WHERE id = $1", id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or(Error::AuthRequestNotFound)
+    }
+
+    /// List pending, unexpired requests for the user owning `device_owner_id`
+    pub async fn list_pending(pool: &PgPool, device_owner_id: i64) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM auth_requests
+             WHERE user_id = $1 AND approved IS NULL AND created_at > $2
+             ORDER BY created_at DESC",
+            device_owner_id,
+            Utc::now() - TTL
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /// List pending, unexpired requests for any of `owner_children`
+    pub async fn list_pending_for(pool: &PgPool, owner_children: &[i64]) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM auth_requests
+             WHERE user_id = ANY($1) AND approved IS NULL AND created_at > $2
+             ORDER BY created_at DESC",
+            owner_children,
+            Utc::now() - TTL
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /// Approve or deny a pending request raised by one of `owner_children`
+    pub async fn respond(
+        pool: &PgPool,
+        id: i64,
+        owner_children: &[i64],
+        approved: bool,
+    ) -> Result<Self, Error> {
+        sqlx::query_as!(
+            Self,
+            "UPDATE auth_requests
+             SET approved = $1, responded_at = now()
+             WHERE id = $2 AND user_id = ANY($3) AND approved IS NULL
+             RETURNING *",
+            approved,
+            id,
+            owner_children
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(Error::AuthRequestNotFound)
+    }
+
+    /// Approve a pending request on behalf of `device_owner_id`
+    pub async fn approve(
+        pool: &PgPool,
+        id: i64,
+        device_id: i64,
+        device_owner_id: i64,
+    ) -> Result<Self, Error> {
+        sqlx::query_as!(
+            Self,
+            "UPDATE auth_requests
+             SET approved = true, approving_device_id = $1, responded_at = now()
+             WHERE id = $2 AND user_id = $3 AND approved IS NULL
+             RETURNING *",
+            device_id,
+            id,
+            device_owner_id
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or(Error::AuthRequestNotFound)
+    }
+
+    /// Redeem an approved, unexpired request for its access code, deleting it (one-time use)
+    pub async fn redeem(pool: &PgPool, id: i64, access_code: &str) -> Result<i64, Error> {
+        let request = Self::get_by_id(pool, id).await?;
+
+        if !constant_time_eq(&request.access_code, access_code) {
+            return Err(Error::AuthRequestNotFound);
+        }
+        if Utc::now() - request.created_at > TTL {
+            sqlx::query!("DELETE FROM auth_requests WHERE id = $1", id)
+                .execute(pool)
+                .await?;
+            return Err(Error::AuthRequestExpired);
+        }
+        if request.approved != Some(true) {
+            return Err(Error::AuthRequestNotApproved);
+        }
+
+        sqlx::query!("DELETE FROM auth_requests WHERE id = $1", id)
+            .execute(pool)
+            .await?;
+
+        Ok(request.user_id)
+    }
+}
@@ -0,0 +1,213 @@
+use axum::{
+    Json,
+    extract::{
+        ConnectInfo,
+        Path,
+        Query,
+        State,
+    },
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
+};
+use garde::Validate;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::net::SocketAddr;
+use utoipa::{
+    IntoParams,
+    IntoResponses,
+    ToSchema,
+};
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    extractors::Validated,
+    features::{
+        auth::{
+            AuthResponse,
+            Session,
+            User,
+            client_ip,
+            device_info,
+            sign,
+        },
+        devices::AuthDevice,
+    },
+    responses::{
+        ApproveAuthRequest,
+        CreateAuthRequest as CreateAuthRequestResponses,
+        ListAuthRequests,
+        RedeemAuthRequest,
+    },
+};
+
+mod db;
+
+pub use db::AuthRequest;
+
+pub const TAG: &str = "Auth Requests";
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(create, list_pending))
+        .routes(routes!(approve))
+        .routes(routes!(token))
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct StartAuthRequest {
+    /// Username of the account requesting the new session
+    #[garde(alphanumeric, length(chars, min = 1))]
+    #[schema(min_length = 1, example = "john")]
+    pub username: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreateAuthRequestResponse {
+    pub id: i64,
+    pub access_code: String,
+}
+
+fn requesting_ip(headers: &HeaderMap) -> String {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("unknown")
+        .to_owned()
+}
+
+/// Start a passwordless login attempt
+///
+/// Returns a request id and access code; an already-authenticated device
+/// belonging to the same user must approve it before it can be redeemed.
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = StartAuthRequest,
+    responses(CreateAuthRequestResponses),
+    tag = TAG
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Validated(payload): Validated<StartAuthRequest>,
+) -> Result<(StatusCode, Json<CreateAuthRequestResponse>), Error> {
+    let user = User::get_by_username(&state.pool, &payload.username).await?;
+    let request = AuthRequest::create(&state.pool, user.id, &requesting_ip(&headers)).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateAuthRequestResponse {
+            id: request.id,
+            access_code: request.access_code,
+        }),
+    ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PendingQuery {
+    #[param(example = true)]
+    pub pending: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct PendingAuthRequest {
+    pub id: i64,
+    pub requesting_ip: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// List pending auth requests for the authenticated device's owner
+#[utoipa::path(
+    get,
+    path = "",
+    params(PendingQuery),
+    responses(ListAuthRequests),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn list_pending(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+    Query(_): Query<PendingQuery>,
+) -> Result<Json<Vec<PendingAuthRequest>>, Error> {
+    let requests = AuthRequest::list_pending(&state.pool, device.owner_id).await?;
+
+    Ok(Json(
+        requests
+            .into_iter()
+            .map(|r| PendingAuthRequest {
+                id: r.id,
+                requesting_ip: r.requesting_ip,
+                created_at: r.created_at,
+            })
+            .collect(),
+    ))
+}
+
+/// Approve a pending auth request
+#[utoipa::path(
+    post,
+    path = "/{id}/approve",
+    responses(ApproveAuthRequest),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn approve(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    AuthRequest::approve(&state.pool, id, device.id, device.owner_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct TokenRequest {
+    #[garde(length(chars, min = 1))]
+    pub access_code: String,
+}
+
+/// Redeem an approved auth request for a normal access token
+#[utoipa::path(
+    post,
+    path = "/{id}/token",
+    request_body = TokenRequest,
+    responses(RedeemAuthRequest),
+    tag = TAG
+)]
+pub async fn token(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+    Validated(payload): Validated<TokenRequest>,
+) -> Result<Json<AuthResponse>, Error> {
+    let user_id = AuthRequest::redeem(&state.pool, id, &payload.access_code).await?;
+    let user = User::get_by_id(&state.pool, user_id).await?;
+
+    if user.blocked {
+        return Err(Error::AccountBlocked);
+    }
+
+    let session = Session::create(
+        &state.pool,
+        user.id,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
+
+    Ok(Json(AuthResponse::new(user, token, Some(session.refresh_token))))
+}
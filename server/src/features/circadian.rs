@@ -1,5 +1,6 @@
 use chrono::{
     DateTime,
+    Datelike,
     Duration,
     NaiveDate,
     NaiveTime,
@@ -13,8 +14,16 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use std::{
+    collections::HashMap,
+    sync::{
+        LazyLock,
+        Mutex,
+    },
+};
 use sunrise::{
     Coordinates,
+    DawnType,
     SolarDay,
     SolarEvent,
 };
@@ -46,6 +55,13 @@ pub struct LightingSchedule {
     pub generated_at: DateTime<Utc>,
     pub valid_until: DateTime<Utc>,
 
+    /// Minute-of-day (local time) the daylight phase starts, after any
+    /// catch-up compression of the morning boost
+    pub daylight_catchup_start_minutes: u32,
+    /// Minute-of-day (local time) the daylight phase ends, after any
+    /// catch-up extension of the evening transition
+    pub daylight_catchup_end_minutes: u32,
+
     /// Lookup table of lighting data points
     pub schedule: Vec<LightingPoint>,
 }
@@ -60,6 +76,74 @@ pub struct LightingPoint {
     pub color_temp: i32,
 }
 
+/// Civil twilight and sun times for a single local day
+#[derive(Debug, Clone, Copy)]
+struct SolarTimes {
+    dawn: NaiveTime,
+    /// Unused by the curve, which anchors to dawn and to dusk (or sunset)
+    #[allow(dead_code)]
+    sunrise: NaiveTime,
+    sunset: NaiveTime,
+    dusk: NaiveTime,
+}
+
+/// The kind of dark interval returned by [`Profile::night_window`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NightWindowKind {
+    /// Sun rises and sets on this day; dark from `sunset` until `sunrise`
+    Normal,
+    /// Polar day: the sun never sets, so there is no dark interval
+    AlwaysLight,
+    /// Polar night: the sun never rises, so the whole day is dark
+    AlwaysDark,
+}
+
+/// The sun-driven dark interval for a single local day, as computed by
+/// [`Profile::night_window`]. `sunrise`/`sunset` are only meaningful when
+/// `kind` is [`NightWindowKind::Normal`]; for the polar edge cases they're
+/// left at the day's midnight UTC.
+#[derive(Debug, Clone, Copy)]
+pub struct NightWindow {
+    pub kind: NightWindowKind,
+    pub sunrise: DateTime<Utc>,
+    pub sunset: DateTime<Utc>,
+}
+
+/// Default duration of the morning ramp-up (Wake -> Daylight)
+const MORNING_BOOST_MINUTES: u32 = 60;
+/// Shortest the morning ramp-up may be compressed to while catching up on
+/// the daily daylight budget
+const MIN_MORNING_BOOST_MINUTES: u32 = 15;
+
+/// The daylight (Phase 2) window boundaries actually used for a schedule,
+/// possibly extended past their natural positions toward
+/// `Profile::min_daylight_minutes` of bright time, within the limits that
+/// [`Profile::compute_daylight_catchup`] applies
+#[derive(Debug, Clone, Copy)]
+struct DaylightCatchup {
+    /// Minute the bright Phase 2 window starts; natural value is
+    /// `wake + MORNING_BOOST_MINUTES`, compressed down to `MIN_MORNING_BOOST_MINUTES`
+    /// if still short on budget after extending the evening side
+    morning_end_minutes: u32,
+    /// Minute the bright Phase 2 window ends; natural value is civil dusk or
+    /// sunset (see [`Profile::compute_daylight_catchup`]), pushed later, up to the
+    /// pre-sleep wind-down, if the natural window falls short of the budget
+    evening_start_minutes: u32,
+}
+
+/// `(lat * 100, lon * 100, date)`, quantized so nearby profiles share a cache entry
+type SolarCacheKey = (i32, i32, NaiveDate);
+
+/// Process-wide cache of [`SolarTimes`] per quantized coordinate and date, since a
+/// high-resolution schedule otherwise recomputes the same `SolarDay` hundreds of times
+static SOLAR_CACHE: LazyLock<Mutex<HashMap<SolarCacheKey, SolarTimes>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn quantize_coordinate(degrees: f64) -> i32 {
+    #[allow(clippy::cast_possible_truncation)]
+    ((degrees * 100.0).round() as i32)
+}
+
 /// Convert local time to seconds since midnight UTC
 fn to_utc_seconds_from_midnight(local_time: NaiveTime, timezone: Tz) -> u32 {
     let now_in_tz = Utc::now().with_timezone(&timezone);
@@ -83,14 +167,17 @@ impl Profile {
     /// Compute a lighting schedule for a profile
     pub fn calculate(&self, points: u16, offset: Duration) -> Result<LightingSchedule, Error> {
         let now = Utc::now();
-        let timezone = self.timezone.parse()?;
+        let timezone = self.tz()?;
+
+        let catchup = self.compute_daylight_catchup(now.with_timezone(&timezone).date_naive())?;
 
         let mut schedule = Vec::with_capacity(points.into());
 
         for timestamp in (0..points.into()).map(|i| now + offset * i) {
             schedule.push(LightingPoint {
                 timestamp,
-                color_temp: self.calculate_at_time(&timestamp.with_timezone(&timezone))?,
+                color_temp: self
+                    .calculate_at_time(&timestamp.with_timezone(&timezone), &catchup)?,
             });
         }
 
@@ -104,13 +191,93 @@ impl Profile {
             motion_timeout_seconds: self.motion_timeout_seconds,
             generated_at: now,
             valid_until: now + offset * points.into(),
+            daylight_catchup_start_minutes: catchup.morning_end_minutes,
+            daylight_catchup_end_minutes: catchup.evening_start_minutes,
             schedule,
         })
     }
 
+    /// Minutes between two minute-of-day values, wrapping past midnight if `to < from`
+    fn minutes_between(from: u32, to: u32) -> u32 {
+        if to >= from { to - from } else { 24 * 60 - from + to }
+    }
+
+    /// Chooses the Phase 2 daylight window boundaries for `date`, extending
+    /// them past their natural positions if needed to guarantee
+    /// `self.min_daylight_minutes` of bright (near-`max_color_temp`) time,
+    /// without intruding into the sleep window or the pre-sleep wind-down.
+    /// The evening transition is pushed later first; if that alone isn't
+    /// enough, the morning boost is compressed (down to a floor) as well.
+    fn compute_daylight_catchup(&self, date: NaiveDate) -> Result<DaylightCatchup, Error> {
+        let (sleep_start, sleep_end) = self.sleep_window_for(date);
+        let sleep_start_minutes = sleep_start.num_seconds_from_midnight() / 60;
+        let wake_minutes = sleep_end.num_seconds_from_midnight() / 60;
+
+        let pre_sleep_minutes = if sleep_start_minutes >= 60 {
+            sleep_start_minutes - 60
+        } else {
+            23 * 60 + sleep_start_minutes
+        };
+
+        let solar_times = if let (Some(lat), Some(lon)) = (self.latitude, self.longitude) {
+            Self::calculate_solar_times(date, lat, lon)?
+        } else {
+            self.estimate_solar_times()
+        };
+        let dusk_minutes = solar_times.dusk.num_seconds_from_midnight() / 60;
+        let sunset_minutes = solar_times.sunset.num_seconds_from_midnight() / 60;
+
+        let natural_evening_start_minutes = if dusk_minutes < pre_sleep_minutes {
+            dusk_minutes
+        } else {
+            sunset_minutes
+        };
+        let natural_morning_end_minutes = (wake_minutes + MORNING_BOOST_MINUTES) % 1440;
+
+        let natural_daylight_minutes =
+            Self::minutes_between(natural_morning_end_minutes, natural_evening_start_minutes);
+
+        #[allow(clippy::cast_sign_loss)]
+        let budget_minutes = self.min_daylight_minutes.max(0) as u32;
+        let mut shortfall = budget_minutes.saturating_sub(natural_daylight_minutes);
+
+        // First, push the evening transition later, up to (but never into) the
+        // pre-sleep wind-down
+        let evening_room = Self::minutes_between(natural_evening_start_minutes, pre_sleep_minutes);
+        let evening_extension = shortfall.min(evening_room);
+        let evening_start_minutes = (natural_evening_start_minutes + evening_extension) % 1440;
+        shortfall -= evening_extension;
+
+        // Still short: compress the morning boost, down to a minimum ramp duration
+        let morning_room = MORNING_BOOST_MINUTES - MIN_MORNING_BOOST_MINUTES;
+        let morning_compression = shortfall.min(morning_room);
+        let morning_end_minutes =
+            (natural_morning_end_minutes + 1440 - morning_compression) % 1440;
+
+        Ok(DaylightCatchup {
+            morning_end_minutes,
+            evening_start_minutes,
+        })
+    }
+
+    /// Sleep window in effect for a given local date, honoring a per-weekday
+    /// override (e.g. sleeping in on weekends) if the profile has one
+    fn sleep_window_for(&self, date: NaiveDate) -> (NaiveTime, NaiveTime) {
+        self.weekday_overrides
+            .iter()
+            .find(|o| o.weekday == date.weekday())
+            .map_or((self.sleep_start, self.sleep_end), |o| {
+                (o.sleep_start, o.sleep_end)
+            })
+    }
+
     /// Calculate color temperature for a specific local time
-    fn calculate_at_time<T: TimeZone>(&self, local_time: &DateTime<T>) -> Result<i32, Error> {
-        let (_, sunset_time) = if let (Some(lat), Some(lon)) = (self.latitude, self.longitude) {
+    fn calculate_at_time<T: TimeZone>(
+        &self,
+        local_time: &DateTime<T>,
+        catchup: &DaylightCatchup,
+    ) -> Result<i32, Error> {
+        let solar_times = if let (Some(lat), Some(lon)) = (self.latitude, self.longitude) {
             Self::calculate_solar_times(local_time.date_naive(), lat, lon)?
         } else {
             self.estimate_solar_times()
@@ -119,18 +286,34 @@ impl Profile {
         // Convert all times to minutes from midnight for easier comparison
         let current_minutes = local_time.time().num_seconds_from_midnight() / 60;
 
-        let sleep_start_minutes = self.sleep_start.num_seconds_from_midnight() / 60;
-        let sleep_end_minutes = self.sleep_end.num_seconds_from_midnight() / 60;
-
-        let sunset_minutes = sunset_time.num_seconds_from_midnight() / 60;
+        let (sleep_start, sleep_end) = self.sleep_window_for(local_time.date_naive());
+        let sleep_start_minutes = sleep_start.num_seconds_from_midnight() / 60;
+        let sleep_end_minutes = sleep_end.num_seconds_from_midnight() / 60;
+
+        // Solar dawn is meant to precede wake by a short "blue hour" window, but
+        // real `calculate_solar_times` output can land dawn at/after wake at high
+        // latitude or in winter; clamp it back to the 30-minute default used by
+        // `estimate_solar_times` so it stays forward-of and close to wake, rather
+        // than letting `interpolate_circadian_curve`'s wraparound `in_range`
+        // match nearly the entire day.
+        let raw_dawn_minutes = solar_times.dawn.num_seconds_from_midnight() / 60;
+        let dawn_minutes = if Self::minutes_between(raw_dawn_minutes, sleep_end_minutes) <= 180 {
+            raw_dawn_minutes
+        } else {
+            (sleep_end_minutes + 24 * 60 - 30) % (24 * 60)
+        };
 
-        // Determine if we're in sleep period
-        let in_sleep_period = if sleep_start_minutes < sleep_end_minutes {
+        // Determine if we're in sleep period. This stops at `dawn_minutes`
+        // rather than `sleep_end_minutes`: the dawn-to-wake slice is instead
+        // handed to `interpolate_circadian_curve`'s Phase 0 below, so that
+        // phase is actually reachable instead of being silently swallowed
+        // here every night.
+        let in_sleep_period = if sleep_start_minutes < dawn_minutes {
             // Sleep doesn't cross midnight (e.g. 2:00-10:00)
-            current_minutes >= sleep_start_minutes && current_minutes < sleep_end_minutes
+            current_minutes >= sleep_start_minutes && current_minutes < dawn_minutes
         } else {
             // Sleep crosses midnight (e.g. 22:00-06:00)
-            current_minutes >= sleep_start_minutes || current_minutes < sleep_end_minutes
+            current_minutes >= sleep_start_minutes || current_minutes < dawn_minutes
         };
 
         if in_sleep_period {
@@ -150,31 +333,125 @@ impl Profile {
             sleep_end_minutes,   // Wake time
             sleep_start_minutes, // Sleep time
             pre_sleep_minutes,   // Wind down time
-            sunset_minutes,
+            catchup.evening_start_minutes,
+            catchup.morning_end_minutes,
+            dawn_minutes,
         ))
     }
 
-    /// Calculate sunrise and sunset times using astronomical algorithms
+    /// Computes the sun-driven dark interval (sunset to sunrise) for `date`
+    /// from this profile's coordinates, using the standard NOAA sunrise/sunset
+    /// approximation rather than `Self::calculate_solar_times`'s `sunrise`-crate
+    /// implementation, since callers that only need "is it dark right now"
+    /// don't need the extra civil-twilight events that API carries.
+    ///
+    /// `sunrise`/`sunset` are only meaningful when the returned `kind` is
+    /// `NightWindowKind::Normal`; for the polar edge cases (`cos ω0` falling
+    /// outside `[-1, 1]`) they're left at `date`'s midnight UTC and the kind
+    /// alone indicates whether the whole day is light or dark.
+    pub fn night_window(&self, date: NaiveDate) -> Result<NightWindow, Error> {
+        let (Some(latitude), Some(longitude)) = (self.latitude, self.longitude) else {
+            return Err(Error::ProfileMissingLocation);
+        };
+
+        let midnight_utc = date.and_time(NaiveTime::MIN).and_utc();
+
+        // Julian day number at Greenwich noon for `date`
+        let julian_day = f64::from(date.num_days_from_ce()) + 1_721_424.5 + 0.5;
+
+        let n = julian_day - 2_451_545.0 + 0.0008;
+        let j_star = n - longitude / 360.0;
+        let solar_mean_anomaly_deg = (357.5291 + 0.985_600_28 * j_star).rem_euclid(360.0);
+        let m = solar_mean_anomaly_deg.to_radians();
+        let equation_of_center =
+            1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+        let ecliptic_longitude_deg =
+            (solar_mean_anomaly_deg + equation_of_center + 282.9372).rem_euclid(360.0);
+        let lambda = ecliptic_longitude_deg.to_radians();
+        let solar_transit =
+            2_451_545.0 + j_star + 0.0053 * m.sin() - 0.0069 * (2.0 * lambda).sin();
+
+        let declination = (23.44_f64.to_radians().sin() * lambda.sin()).asin();
+        let phi = latitude.to_radians();
+
+        let cos_hour_angle = ((-0.833_f64).to_radians().sin() - phi.sin() * declination.sin())
+            / (phi.cos() * declination.cos());
+
+        if cos_hour_angle >= 1.0 {
+            return Ok(NightWindow {
+                kind: NightWindowKind::AlwaysDark,
+                sunrise: midnight_utc,
+                sunset: midnight_utc,
+            });
+        }
+        if cos_hour_angle <= -1.0 {
+            return Ok(NightWindow {
+                kind: NightWindowKind::AlwaysLight,
+                sunrise: midnight_utc,
+                sunset: midnight_utc,
+            });
+        }
+
+        let hour_angle_deg = cos_hour_angle.acos().to_degrees();
+        let j_rise = solar_transit - hour_angle_deg / 360.0;
+        let j_set = solar_transit + hour_angle_deg / 360.0;
+
+        #[allow(clippy::cast_possible_truncation)]
+        Ok(NightWindow {
+            kind: NightWindowKind::Normal,
+            sunrise: midnight_utc
+                + Duration::seconds(((j_rise - julian_day + 0.5) * 86_400.0) as i64),
+            sunset: midnight_utc
+                + Duration::seconds(((j_set - julian_day + 0.5) * 86_400.0) as i64),
+        })
+    }
+
+    /// Calculate civil twilight and sun times using astronomical algorithms, memoized
+    /// per quantized `(latitude, longitude, date)` since many schedule points and
+    /// profiles tend to share the same day and general location
     fn calculate_solar_times(
         date: NaiveDate,
         latitude: f64,
         longitude: f64,
-    ) -> Result<(NaiveTime, NaiveTime), Error> {
+    ) -> Result<SolarTimes, Error> {
+        let key = (
+            quantize_coordinate(latitude),
+            quantize_coordinate(longitude),
+            date,
+        );
+
+        #[allow(clippy::unwrap_used)]
+        let mut cache = SOLAR_CACHE.lock().unwrap();
+
+        if let Some(solar_times) = cache.get(&key) {
+            return Ok(*solar_times);
+        }
+
         let coord = Coordinates::new(latitude, longitude).ok_or(Error::DataCorruption(format!(
             "Invalid coordinates: {latitude}, {longitude}"
         )))?;
         let solar_day = SolarDay::new(coord, date);
 
-        let sunrise = solar_day.event_time(SolarEvent::Sunrise).time();
-        let sunset = solar_day.event_time(SolarEvent::Sunset).time();
+        let solar_times = SolarTimes {
+            dawn: solar_day.event_time(SolarEvent::Dawn(DawnType::Civil)).time(),
+            sunrise: solar_day.event_time(SolarEvent::Sunrise).time(),
+            sunset: solar_day.event_time(SolarEvent::Sunset).time(),
+            dusk: solar_day.event_time(SolarEvent::Dusk(DawnType::Civil)).time(),
+        };
+
+        // Bound growth: drop anything older than yesterday before inserting the new entry
+        let yesterday = Utc::now().date_naive() - Duration::days(1);
+        cache.retain(|(_, _, date), _| *date >= yesterday);
+        cache.insert(key, solar_times);
 
-        Ok((sunrise, sunset))
+        Ok(solar_times)
     }
 
     /// Estimate solar times based on sleep schedule when location is missing:
     /// - Sunrise aligns with Wake Up time (user needs light to wake up).
     /// - Sunset is approximated 2 hours before Sleep Start to allow for an evening relaxation phase.
-    fn estimate_solar_times(&self) -> (NaiveTime, NaiveTime) {
+    /// - Dawn/dusk are approximated 30 minutes either side of sunrise/sunset.
+    fn estimate_solar_times(&self) -> SolarTimes {
         let sleep_start_seconds = self.sleep_start.num_seconds_from_midnight();
         let offset_seconds = 2 * 3600; // 2 hours
 
@@ -187,29 +464,60 @@ impl Profile {
         };
 
         #[allow(clippy::expect_used)]
-        let sunset = NaiveTime::from_num_seconds_from_midnight_opt(sunset_seconds, 0)
-            .unwrap_or(NaiveTime::from_hms_opt(20, 0, 0).expect("valid time"));
-
-        (self.sleep_end, sunset)
+        let fallback = NaiveTime::from_hms_opt(20, 0, 0).expect("valid time");
+        let sunset =
+            NaiveTime::from_num_seconds_from_midnight_opt(sunset_seconds, 0).unwrap_or(fallback);
+
+        let twilight_offset_seconds = 30 * 60; // 30 minutes
+        let sunrise = self.sleep_end;
+
+        let dawn = NaiveTime::from_num_seconds_from_midnight_opt(
+            (sunrise.num_seconds_from_midnight() + 24 * 3600 - twilight_offset_seconds)
+                % (24 * 3600),
+            0,
+        )
+        .unwrap_or(sunrise);
+        let dusk = NaiveTime::from_num_seconds_from_midnight_opt(
+            (sunset_seconds + twilight_offset_seconds) % (24 * 3600),
+            0,
+        )
+        .unwrap_or(sunset);
+
+        SolarTimes {
+            dawn,
+            sunrise,
+            sunset,
+            dusk,
+        }
     }
 
-    /// Calculates curve with 4 phases:
+    /// Calculates curve with 5 phases:
+    /// 0. Civil Dawn -> Wake (Min -> Pre-Wake)
     /// 1. Wake -> Morning Boost (Min -> Max)
-    /// 2. Day -> Sunset (Hold Max)
-    /// 3. Sunset -> Pre-Sleep (Max -> Relaxation Temp)
+    /// 2. Day -> Evening Start (Hold Max)
+    /// 3. Evening Start -> Pre-Sleep (Max -> Relaxation Temp)
     /// 4. Pre-Sleep -> Sleep (Relaxation Temp -> Min)
+    ///
+    /// "Evening Start" and "Morning Boost" end may both be pushed past their
+    /// natural positions to guarantee `min_daylight_minutes` of Phase 2; see
+    /// [`Self::compute_daylight_catchup`].
     fn interpolate_circadian_curve(
         &self,
         current_minutes: u32,
         wake_minutes: u32,
         sleep_minutes: u32,
         pre_sleep_minutes: u32,
-        sunset_minutes: u32,
+        evening_start_minutes: u32,
+        morning_end_minutes: u32,
+        dawn_minutes: u32,
     ) -> i32 {
         // Define an "Evening/Relaxation" temperature
         // This is warmer than daylight but brighter than nightlight
         // e.g. if Max=6500, Min=2000, Relax = 3500
         let relax_temp = self.min_color_temp + (self.max_color_temp - self.min_color_temp) / 3;
+        // A dim "pre-wake" temperature, a quarter of the way from min to max,
+        // that Phase 0 ramps up to; Phase 1 then starts again from min
+        let pre_wake_temp = self.min_color_temp + (self.max_color_temp - self.min_color_temp) / 4;
 
         // Helper to check time range which may wrap midnight
         let in_range = |curr: u32, start: u32, end: u32| -> bool {
@@ -241,8 +549,16 @@ impl Profile {
             }
         };
 
-        // Morning ramp-up duration, 60 mins after waking
-        let morning_end_minutes = (wake_minutes + 60) % 1440;
+        // PHASE 0: Pre-Dawn "Blue Hour" (Civil Dawn -> Wake)
+        // Gentle warm glow anchored to twilight instead of waiting for the wake alarm
+        if in_range(current_minutes, dawn_minutes, wake_minutes) {
+            let t = get_t(current_minutes, dawn_minutes, wake_minutes);
+            // Ease-in (slow start, quick finish towards wake)
+            let t_eased = t.powi(2);
+            #[allow(clippy::cast_possible_truncation)]
+            return self.min_color_temp
+                + (f64::from(pre_wake_temp - self.min_color_temp) * t_eased) as i32;
+        }
 
         // PHASE 1: Morning Boost (Wake -> Wake+1h)
         if in_range(current_minutes, wake_minutes, morning_end_minutes) {
@@ -265,14 +581,14 @@ impl Profile {
             return relax_temp - (f64::from(relax_temp - self.min_color_temp) * t_eased) as i32;
         }
 
-        // PHASE 3: Evening Relaxation (Sunset -> Pre-Sleep)
-        // We need to check if Sunset happens before Pre-Sleep
-        // If Sunset is super late (summer) or after pre_sleep, we skip this logic
-        // NOTE: We use sunset_minutes as start, pre_sleep_minutes as end.
-        if in_range(current_minutes, sunset_minutes, pre_sleep_minutes) {
+        // PHASE 3: Evening Relaxation (Evening Start -> Pre-Sleep)
+        // We need to check if the evening start happens before Pre-Sleep
+        // If it's super late (summer) or after pre_sleep, we skip this logic
+        // NOTE: We use evening_start_minutes as start, pre_sleep_minutes as end.
+        if in_range(current_minutes, evening_start_minutes, pre_sleep_minutes) {
             // Determine effective start/end for interpolation
-            // If we are here, we are between sunset and pre-sleep
-            let t = get_t(current_minutes, sunset_minutes, pre_sleep_minutes);
+            // If we are here, we are between the evening start and pre-sleep
+            let t = get_t(current_minutes, evening_start_minutes, pre_sleep_minutes);
             // Linear drop from Max to Relax
             #[allow(clippy::cast_possible_truncation)]
             return self.max_color_temp - (f64::from(self.max_color_temp - relax_temp) * t) as i32;
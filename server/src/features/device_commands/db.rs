@@ -0,0 +1,204 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use garde::{
+    Valid,
+    Validate,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+use sqlx::{
+    PgPool,
+    types::Json,
+};
+use utoipa::ToSchema;
+
+use crate::errors::Error;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeviceCommand {
+    pub id: i64,
+    pub device_id: i64,
+
+    /// Opaque payload interpreted by firmware, e.g. `{"set_color_temp": 4000}`
+    #[schema(value_type = Object)]
+    pub command: Json<Value>,
+
+    pub created_at: DateTime<Utc>,
+    /// When the device's drain endpoint last returned this command, if at all
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// When the device confirmed it acted on this command, if at all
+    pub acked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[garde(allow_unvalidated)]
+#[schema(as = CreateDeviceCommandRequest)]
+pub struct CreateDeviceCommand {
+    #[schema(value_type = Object)]
+    pub command: Value,
+}
+
+/// Persistence surface for [`DeviceCommand`], extracted so a non-Postgres
+/// backend can be plugged in instead of this crate hard-coding `PgPool`
+/// everywhere. The Postgres implementation lives behind the `postgres`
+/// feature; [`DeviceCommand`]'s inherent methods are thin generic wrappers
+/// so existing call sites (already passing `&state.pool`) don't need to change.
+#[allow(async_fn_in_trait)]
+pub trait DeviceCommandStore {
+    async fn enqueue(
+        &self,
+        device_id: i64,
+        data: Valid<CreateDeviceCommand>,
+    ) -> Result<DeviceCommand, Error>;
+    async fn list_pending(&self, device_id: i64) -> Result<Vec<DeviceCommand>, Error>;
+    async fn list_as_owner(&self, owner_id: i64) -> Result<Vec<DeviceCommand>, Error>;
+    async fn list_as_user(&self, user_id: i64, parent_id: i64) -> Result<Vec<DeviceCommand>, Error>;
+    async fn mark_delivered(&self, id: i64) -> Result<DeviceCommand, Error>;
+    async fn mark_acked(&self, id: i64, device_id: i64) -> Result<DeviceCommand, Error>;
+}
+
+#[cfg(feature = "postgres")]
+impl DeviceCommandStore for PgPool {
+    async fn enqueue(
+        &self,
+        device_id: i64,
+        data: Valid<CreateDeviceCommand>,
+    ) -> Result<DeviceCommand, Error> {
+        Ok(sqlx::query_as!(
+            DeviceCommand,
+            r#"INSERT INTO device_commands (device_id, command)
+               VALUES ($1, $2)
+               RETURNING id, device_id, command AS "command: Json<Value>",
+                         created_at, delivered_at, acked_at"#,
+            device_id,
+            data.command
+        )
+        .fetch_one(self)
+        .await?)
+    }
+
+    async fn list_pending(&self, device_id: i64) -> Result<Vec<DeviceCommand>, Error> {
+        Ok(sqlx::query_as!(
+            DeviceCommand,
+            r#"SELECT id, device_id, command AS "command: Json<Value>",
+                      created_at, delivered_at, acked_at
+               FROM device_commands
+               WHERE device_id = $1 AND delivered_at IS NULL
+               ORDER BY created_at ASC"#,
+            device_id
+        )
+        .fetch_all(self)
+        .await?)
+    }
+
+    async fn list_as_owner(&self, owner_id: i64) -> Result<Vec<DeviceCommand>, Error> {
+        Ok(sqlx::query_as!(
+            DeviceCommand,
+            r#"SELECT c.id, c.device_id, c.command AS "command: Json<Value>",
+                      c.created_at, c.delivered_at, c.acked_at
+               FROM device_commands c
+               INNER JOIN devices d ON d.id = c.device_id
+               WHERE d.owner_id = $1 OR (
+                   d.owner_id IN (SELECT id FROM users WHERE parent_id = $1) AND d.is_public = true
+               )
+               ORDER BY c.created_at DESC"#,
+            owner_id
+        )
+        .fetch_all(self)
+        .await?)
+    }
+
+    async fn list_as_user(&self, user_id: i64, parent_id: i64) -> Result<Vec<DeviceCommand>, Error> {
+        Ok(sqlx::query_as!(
+            DeviceCommand,
+            r#"SELECT c.id, c.device_id, c.command AS "command: Json<Value>",
+                      c.created_at, c.delivered_at, c.acked_at
+               FROM device_commands c
+               INNER JOIN devices d ON d.id = c.device_id
+               WHERE d.owner_id = $1 OR (d.owner_id = $2 AND d.is_public = true)
+               ORDER BY c.created_at DESC"#,
+            user_id,
+            parent_id
+        )
+        .fetch_all(self)
+        .await?)
+    }
+
+    async fn mark_delivered(&self, id: i64) -> Result<DeviceCommand, Error> {
+        sqlx::query_as!(
+            DeviceCommand,
+            r#"UPDATE device_commands SET delivered_at = now()
+               WHERE id = $1
+               RETURNING id, device_id, command AS "command: Json<Value>",
+                         created_at, delivered_at, acked_at"#,
+            id
+        )
+        .fetch_optional(self)
+        .await?
+        .ok_or(Error::DeviceCommandNotFound)
+    }
+
+    async fn mark_acked(&self, id: i64, device_id: i64) -> Result<DeviceCommand, Error> {
+        sqlx::query_as!(
+            DeviceCommand,
+            r#"UPDATE device_commands SET acked_at = now()
+               WHERE id = $1 AND device_id = $2
+               RETURNING id, device_id, command AS "command: Json<Value>",
+                         created_at, delivered_at, acked_at"#,
+            id,
+            device_id
+        )
+        .fetch_optional(self)
+        .await?
+        .ok_or(Error::DeviceCommandNotFound)
+    }
+}
+
+impl DeviceCommand {
+    /// Queue a command for a device to pick up on its next drain
+    pub async fn enqueue(
+        store: &impl DeviceCommandStore,
+        device_id: i64,
+        data: Valid<CreateDeviceCommand>,
+    ) -> Result<Self, Error> {
+        store.enqueue(device_id, data).await
+    }
+
+    /// Commands a device hasn't drained yet, oldest first
+    pub async fn list_pending(store: &impl DeviceCommandStore, device_id: i64) -> Result<Vec<Self>, Error> {
+        store.list_pending(device_id).await
+    }
+
+    /// Commands for owner's devices and their users' public devices
+    pub async fn list_as_owner(store: &impl DeviceCommandStore, owner_id: i64) -> Result<Vec<Self>, Error> {
+        store.list_as_owner(owner_id).await
+    }
+
+    /// Commands for user's devices and their parent's public devices
+    pub async fn list_as_user(
+        store: &impl DeviceCommandStore,
+        user_id: i64,
+        parent_id: i64,
+    ) -> Result<Vec<Self>, Error> {
+        store.list_as_user(user_id, parent_id).await
+    }
+
+    /// Stamps `delivered_at`, marking the command as handed to the device
+    pub async fn mark_delivered(store: &impl DeviceCommandStore, id: i64) -> Result<Self, Error> {
+        store.mark_delivered(id).await
+    }
+
+    /// Stamps `acked_at`, confirming the device acted on the command
+    pub async fn mark_acked(
+        store: &impl DeviceCommandStore,
+        id: i64,
+        device_id: i64,
+    ) -> Result<Self, Error> {
+        store.mark_acked(id, device_id).await
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    Json,
+    extract::{
+        Path,
+        State,
+    },
+    http::StatusCode,
+};
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    encoded_id::EncodedId,
+    errors::Error,
+    extractors::Validated,
+    features::{
+        auth::{
+            Authenticated,
+            Role,
+        },
+        devices::{
+            AuthDevice,
+            Device,
+        },
+    },
+    responses::{
+        AckDeviceCommand,
+        DrainDeviceCommands,
+        GetDeviceCommands,
+        PostDeviceCommand,
+    },
+};
+
+mod db;
+
+use db::CreateDeviceCommand;
+
+pub use db::DeviceCommand;
+
+pub const TAG: &str = "Device Commands";
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(get_all))
+        .routes(routes!(post))
+        .routes(routes!(poll))
+        .routes(routes!(ack))
+}
+
+/// List device commands
+///
+/// - Owner gets commands for their devices and their Users' public devices.
+/// - User gets commands for their devices and their Owner's public devices.
+#[utoipa::path(
+    get,
+    path = "",
+    responses(GetDeviceCommands),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn get_all(
+    State(state): State<AppState>,
+    auth: Authenticated,
+) -> Result<Json<Vec<DeviceCommand>>, Error> {
+    Ok(Json(match auth.role {
+        Role::Admin | Role::Owner => DeviceCommand::list_as_owner(&state.pool, auth.id).await?,
+        Role::User(parent) => DeviceCommand::list_as_user(&state.pool, auth.id, parent).await?,
+    }))
+}
+
+/// Queue a command for a device
+///
+/// Owner may queue commands **only** for their own devices. The device picks
+/// it up on its next `GET /device-commands/poll`.
+#[utoipa::path(
+    post,
+    path = "/device/{device_id}",
+    request_body = CreateDeviceCommand,
+    responses(PostDeviceCommand),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn post(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(device_id): Path<EncodedId>,
+    Validated(data): Validated<CreateDeviceCommand>,
+) -> Result<(StatusCode, Json<DeviceCommand>), Error> {
+    let device_id = device_id.decode().ok_or(Error::DeviceNotFound)?;
+    let device = Device::get_by_id(&state.pool, device_id).await?;
+
+    match auth.role {
+        Role::Admin => (),
+        Role::Owner | Role::User(_) if device.owner_id == auth.id => (),
+        _ => return Err(Error::DeviceNotFound),
+    }
+
+    let command = DeviceCommand::enqueue(&state.pool, device_id, data).await?;
+
+    Ok((StatusCode::CREATED, Json(command)))
+}
+
+/// Drain pending commands
+///
+/// Called by devices holding an API key. Returns every command queued since
+/// the last drain and marks them delivered, so firmware can poll this
+/// alongside `POST /devices/{id}/poll` instead of waiting for push delivery.
+#[utoipa::path(
+    get,
+    path = "/poll",
+    responses(DrainDeviceCommands),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn poll(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+) -> Result<Json<Vec<DeviceCommand>>, Error> {
+    let pending = DeviceCommand::list_pending(&state.pool, device.id).await?;
+
+    let mut delivered = Vec::with_capacity(pending.len());
+    for command in pending {
+        delivered.push(DeviceCommand::mark_delivered(&state.pool, command.id).await?);
+    }
+
+    Ok(Json(delivered))
+}
+
+/// Acknowledge a command
+///
+/// Called by devices holding an API key, once they've acted on a command
+/// returned from `GET /device-commands/poll`.
+#[utoipa::path(
+    post,
+    path = "/{id}/ack",
+    responses(AckDeviceCommand),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn ack(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+    Path(id): Path<EncodedId>,
+) -> Result<Json<DeviceCommand>, Error> {
+    let id = id.decode().ok_or(Error::DeviceCommandNotFound)?;
+
+    Ok(Json(
+        DeviceCommand::mark_acked(&state.pool, id, device.id).await?,
+    ))
+}
@@ -1,11 +1,38 @@
 use axum::{
+    RequestPartsExt,
     extract::FromRequestParts,
     http::request::Parts,
 };
+use axum_extra::{
+    TypedHeader,
+    headers::{
+        Authorization,
+        authorization::Bearer,
+    },
+};
+use chrono::{
+    Duration,
+    Utc,
+};
+use jsonwebtoken::{
+    Header,
+    Validation,
+    decode,
+    encode,
+    errors::ErrorKind,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 use crate::{
     AppState,
     errors::Error,
+    features::auth::{
+        JwtConfig,
+        User,
+    },
 };
 
 use super::Device;
@@ -30,6 +57,87 @@ impl FromRequestParts<AppState> for AuthDevice {
             .await?
             .ok_or(Error::TokenExpired)?;
 
+        // Re-checked on every request so blocking the owner immediately cuts
+        // off their devices too, not just their own JWT
+        if User::is_blocked(&state.pool, device.owner_id).await? {
+            return Err(Error::AccountBlocked);
+        }
+
+        Device::touch_last_seen(&state.pool, device.id).await?;
+
         Ok(Self(device))
     }
 }
+
+/// A capability a device-scoped token can be restricted to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceScope {
+    TelemetryWrite,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceClaims {
+    device_id: i64,
+    scope: DeviceScope,
+    exp: u64,
+}
+
+/// Signs a short-lived token scoped to a single device and capability
+pub fn sign_device_token(
+    device_id: i64,
+    scope: DeviceScope,
+    keys: &JwtConfig,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    #[allow(clippy::expect_used)]
+    let expiration = Utc::now()
+        .checked_add_signed(Duration::hours(1))
+        .expect("never out of date")
+        .timestamp();
+    let claims = DeviceClaims {
+        device_id,
+        scope,
+        exp: expiration.cast_unsigned(),
+    };
+
+    encode(&Header::new(keys.algorithm), &claims, &keys.encoding_key)
+}
+
+/// A token minted by [`sign_device_token`], scoped to pushing telemetry for one device
+pub struct TelemetryWriteToken {
+    pub device_id: i64,
+}
+
+impl FromRequestParts<AppState> for TelemetryWriteToken {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await?;
+
+        let claims = decode::<DeviceClaims>(
+            bearer.token(),
+            &state.jwt.decoding_key,
+            &Validation::new(state.jwt.algorithm),
+        )
+        .map(|data| data.claims)
+        .map_err(|e| match e.kind() {
+            ErrorKind::ExpiredSignature => Error::TokenExpired,
+            _ => Error::InvalidToken,
+        })?;
+
+        if claims.scope != DeviceScope::TelemetryWrite {
+            return Err(Error::InvalidToken);
+        }
+
+        Device::touch_last_seen(&state.pool, claims.device_id).await?;
+
+        Ok(Self {
+            device_id: claims.device_id,
+        })
+    }
+}
@@ -17,21 +17,42 @@ use serde::{
 use sqlx::PgPool;
 use utoipa::ToSchema;
 
-use crate::errors::Error;
+use crate::{
+    errors::Error,
+    features::{
+        audit,
+        auth::Role,
+    },
+};
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct Device {
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub id: i64,
     pub name: String,
     pub secret_key: String,
 
+    #[serde(serialize_with = "crate::encoded_id::serialize_opt")]
+    #[schema(value_type = Option<String>, example = "Uk3xQ9")]
     pub profile_id: Option<i64>,
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub owner_id: i64,
     pub is_public: bool,
 
     pub firmware_version: Option<String>,
     pub last_seen: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+
+    /// Last time this device checked in via `POST /{id}/poll`
+    pub last_poll: Option<DateTime<Utc>>,
+    /// Self-reported seconds since the device last restarted, from its most recent poll
+    pub uptime_seconds: Option<i64>,
+
+    /// Opaque push-transport token (e.g. an FCM registration token) used to
+    /// nudge the device to pull a fresh schedule. Never exposed to non-owners.
+    pub push_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -47,6 +68,11 @@ pub struct CreateDevice {
 
     #[schema(default = true)]
     pub is_public: bool,
+
+    /// Opaque push-transport token (e.g. an FCM registration token); settable
+    /// only by the device's owner
+    #[schema(example = "fcm:eXaMpLeToken")]
+    pub push_token: Option<String>,
 }
 
 impl Device {
@@ -66,13 +92,14 @@ impl Device {
     ) -> Result<Self, Error> {
         Ok(sqlx::query_as!(
             Self,
-            "INSERT INTO devices (owner_id, name, profile_id, is_public, secret_key)
-            VALUES ($1, $2, $3, $4, $5) RETURNING *",
+            "INSERT INTO devices (owner_id, name, profile_id, is_public, secret_key, push_token)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING *",
             owner_id,
             data.name,
             data.profile_id,
             data.is_public,
-            Self::generate_key()
+            Self::generate_key(),
+            data.push_token
         )
         .fetch_one(pool)
         .await?)
@@ -118,17 +145,24 @@ impl Device {
     }
 
     /// Transactional update helper
-    pub async fn update<F>(pool: &PgPool, id: i64, func: F) -> Result<Self, Error>
+    pub async fn update<F>(
+        pool: &PgPool,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<Self, Error>
     where
         F: FnOnce(&mut Self) -> Result<bool, Error>,
     {
         let mut tx = pool.begin().await?;
 
-        let mut device =
+        let before =
             sqlx::query_as!(Self, "SELECT * FROM devices WHERE id = $1 FOR UPDATE", id)
                 .fetch_optional(&mut *tx)
                 .await?
                 .ok_or(Error::DeviceNotFound)?;
+        let mut device = before.clone();
 
         let updated = func(&mut device)?;
 
@@ -151,25 +185,47 @@ impl Device {
                 profile_id = $3,
                 is_public = $4,
                 firmware_version = $5,
-                last_seen = $6
-             WHERE id = $7 RETURNING *",
+                last_seen = $6,
+                push_token = $7
+             WHERE id = $8 RETURNING *",
             device.name,
             device.secret_key,
             device.profile_id,
             device.is_public,
             device.firmware_version,
             device.last_seen,
+            device.push_token,
             device.id
         )
         .fetch_one(&mut *tx)
         .await?;
 
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "update",
+            "device",
+            device.id,
+            audit::diff(
+                &serde_json::to_value(&before).expect("Device always serializes to JSON"),
+                &serde_json::to_value(&device).expect("Device always serializes to JSON"),
+            ),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(device)
     }
 
-    pub async fn delete<F>(pool: &PgPool, id: i64, func: F) -> Result<(), Error>
+    pub async fn delete<F>(
+        pool: &PgPool,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<(), Error>
     where
         F: FnOnce(&mut Self) -> Result<bool, Error>,
     {
@@ -198,6 +254,17 @@ impl Device {
             .execute(&mut *tx)
             .await?;
 
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "delete",
+            "device",
+            device.id,
+            serde_json::to_value(&device).expect("Device always serializes to JSON"),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(())
@@ -220,6 +287,10 @@ impl Device {
             self.is_public = new.is_public;
             updated = true;
         }
+        if self.push_token != new.push_token {
+            self.push_token = new.push_token;
+            updated = true;
+        }
 
         updated
     }
@@ -228,4 +299,49 @@ impl Device {
         self.secret_key = Self::generate_key();
         true
     }
+
+    /// Clears `push_token` unless `viewer_id` is the device's literal owner
+    pub fn redact_push_token_for(&mut self, viewer_id: i64) {
+        if self.owner_id != viewer_id {
+            self.push_token = None;
+        }
+    }
+
+    /// Stamps `last_seen` with the current time, without a full select/update round trip
+    pub async fn touch_last_seen(pool: &PgPool, id: i64) -> Result<(), Error> {
+        sqlx::query!("UPDATE devices SET last_seen = now() WHERE id = $1", id)
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Records a check-in: stamps `last_poll` and stores the self-reported `uptime_seconds`,
+    /// nulling it out if the device didn't report one
+    pub async fn poll(pool: &PgPool, id: i64, uptime_seconds: Option<i64>) -> Result<(), Error> {
+        sqlx::query!(
+            "UPDATE devices SET last_poll = now(), uptime_seconds = $1 WHERE id = $2",
+            uptime_seconds,
+            id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Devices bound to a profile that have registered a push token, i.e. the
+    /// set worth notifying when that profile's schedule changes
+    pub async fn list_by_profile_with_push_token(
+        pool: &PgPool,
+        profile_id: i64,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(sqlx::query_as!(
+            Self,
+            "SELECT * FROM devices WHERE profile_id = $1 AND push_token IS NOT NULL",
+            profile_id
+        )
+        .fetch_all(pool)
+        .await?)
+    }
 }
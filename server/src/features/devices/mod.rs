@@ -6,6 +6,8 @@ use axum::{
     },
     http::StatusCode,
 };
+use chrono::Duration;
+use garde::Validate;
 use utoipa_axum::{
     router::OpenApiRouter,
     routes,
@@ -13,6 +15,7 @@ use utoipa_axum::{
 
 use crate::{
     AppState,
+    encoded_id::EncodedId,
     errors::Error,
     extractors::Validated,
     features::{
@@ -21,12 +24,18 @@ use crate::{
             Role,
             User,
         },
-        profiles,
+        circadian::LightingSchedule,
+        profiles::{
+            self,
+            Profile,
+        },
     },
     responses::{
         DeleteDevice,
         GetDevice,
         GetDevices,
+        IssueDeviceToken,
+        PollDevice,
         PostDevice,
         PutDevice,
         RegenerateDeviceKey,
@@ -35,18 +44,36 @@ use crate::{
 
 mod auth;
 mod db;
+mod push;
 
 use db::CreateDevice;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use utoipa::ToSchema;
 
-pub use auth::AuthDevice;
+pub use auth::{
+    AuthDevice,
+    DeviceScope,
+    TelemetryWriteToken,
+    sign_device_token,
+};
 pub use db::Device;
+pub use push::{
+    LogPushTransport,
+    PushTransport,
+};
 
 pub const TAG: &str = "Devices";
 
+/// Number of lighting points returned per schedule, one hour apart
+const SCHEDULE_POINTS: u16 = 24;
+
 pub fn router() -> OpenApiRouter<AppState> {
     OpenApiRouter::new()
         .routes(routes!(post, get_all))
-        .routes(routes!(get, put, delete, regenerate_key))
+        .routes(routes!(get, put, delete, regenerate_key, issue_token, poll))
 }
 
 /// Get device info
@@ -64,9 +91,14 @@ pub fn router() -> OpenApiRouter<AppState> {
 pub async fn get(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<Json<Device>, Error> {
-    let device = Device::get_by_id(&state.pool, id).await?;
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
+    let mut device = Device::get_by_id(&state.pool, id).await?;
+
+    if !matches!(auth.role, Role::Admin) {
+        device.redact_push_token_for(auth.id);
+    }
 
     Ok(Json(match auth.role {
         Role::Admin => device,
@@ -97,10 +129,18 @@ pub async fn get_all(
     State(state): State<AppState>,
     auth: Authenticated,
 ) -> Result<Json<Vec<Device>>, Error> {
-    Ok(Json(match auth.role {
+    let mut devices = match auth.role {
         Role::Admin | Role::Owner => Device::list_as_owner(&state.pool, auth.id).await?,
         Role::User(parent) => Device::list_as_user(&state.pool, auth.id, parent).await?,
-    }))
+    };
+
+    if !matches!(auth.role, Role::Admin) {
+        for device in &mut devices {
+            device.redact_push_token_for(auth.id);
+        }
+    }
+
+    Ok(Json(devices))
 }
 
 /// Create a new device
@@ -141,9 +181,10 @@ pub async fn post(
 pub async fn put(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
     Validated(payload): Validated<CreateDevice>,
 ) -> Result<Json<Device>, Error> {
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
     let children = User::get_children(&state.pool, auth.id).await?;
 
     // HACK: permissions check
@@ -152,19 +193,25 @@ pub async fn put(
     }
 
     let payload = payload.into_inner();
-    let device = Device::update(&state.pool, id, |device| match auth.role {
+    let device = Device::update(&state.pool, auth.id, auth.role, id, |device| match auth.role {
         Role::Admin => Ok(device.patch(payload)),
         Role::Owner | Role::User(_) if device.owner_id == auth.id => Ok(device.patch(payload)),
         Role::User(parent) if device.owner_id == parent && device.is_public => {
             if !payload.is_public {
                 return Err(Error::CannotSetOthersDevicePrivate);
             }
+            if payload.push_token.is_some() {
+                return Err(Error::CannotSetOthersPushToken);
+            }
             Ok(device.patch(payload))
         }
         Role::Owner if device.is_public && children.contains(&device.owner_id) => {
             if !payload.is_public {
                 return Err(Error::CannotSetOthersDevicePrivate);
             }
+            if payload.push_token.is_some() {
+                return Err(Error::CannotSetOthersPushToken);
+            }
             Ok(device.patch(payload))
         }
         _ => Err(Error::DeviceNotFound),
@@ -189,11 +236,12 @@ pub async fn put(
 pub async fn regenerate_key(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<Json<Device>, Error> {
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
     let children = User::get_children(&state.pool, auth.id).await?;
 
-    let device = Device::update(&state.pool, id, |device| match auth.role {
+    let device = Device::update(&state.pool, auth.id, auth.role, id, |device| match auth.role {
         Role::Admin => Ok(device.regenerate_key()),
         Role::Owner | Role::User(_) if device.owner_id == auth.id => Ok(device.regenerate_key()),
         Role::User(parent) if device.owner_id == parent && device.is_public => {
@@ -206,9 +254,109 @@ pub async fn regenerate_key(
     })
     .await?;
 
+    // The old key is now invalid, so firmware holding a stale token/key needs
+    // to come back and re-authenticate; nudge it rather than waiting on it
+    if let Some(push_token) = &device.push_token {
+        state.push.notify_schedule_changed(push_token);
+    }
+
     Ok(Json(device))
 }
 
+/// Notifies every device bound to `profile_id` that has registered a push
+/// token, so lamps pull a fresh schedule instead of waiting for `valid_until`
+/// to lapse.
+pub async fn notify_profile_changed(state: &AppState, profile_id: i64) -> Result<(), Error> {
+    let devices = Device::list_by_profile_with_push_token(&state.pool, profile_id).await?;
+
+    for device in devices {
+        if let Some(push_token) = &device.push_token {
+            state.push.notify_schedule_changed(push_token);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DeviceTokenResponse {
+    pub token: String,
+}
+
+/// Exchange a device's secret key for a scoped telemetry-push token
+///
+/// The returned token is short-lived and can only be used against
+/// `POST /telemetry`, restricted to this single device id. This lets
+/// firmware hold a narrowly-scoped token day-to-day instead of the
+/// full secret key.
+#[utoipa::path(
+    post,
+    path = "/{id}/token",
+    responses(IssueDeviceToken),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn issue_token(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+    Path(id): Path<EncodedId>,
+) -> Result<Json<DeviceTokenResponse>, Error> {
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
+    if device.id != id {
+        return Err(Error::DeviceNotFound);
+    }
+
+    let token = sign_device_token(device.id, DeviceScope::TelemetryWrite, &state.jwt)?;
+
+    Ok(Json(DeviceTokenResponse { token }))
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct PollDeviceRequest {
+    /// Seconds since the device last restarted; omit if unknown
+    #[garde(skip)]
+    #[schema(example = 3600)]
+    pub uptime_seconds: Option<i64>,
+}
+
+/// Device check-in
+///
+/// Firmware calls this periodically to report liveness and uptime, and to
+/// pick up a fresh lighting schedule in the same round trip. Authenticating
+/// the request already stamps `last_seen`; this additionally stamps `last_poll`
+/// and stores the self-reported uptime.
+#[utoipa::path(
+    post,
+    path = "/{id}/poll",
+    request_body = PollDeviceRequest,
+    responses(PollDevice),
+    tag = TAG,
+    security(("api_key" = []))
+)]
+pub async fn poll(
+    State(state): State<AppState>,
+    AuthDevice(device): AuthDevice,
+    Path(id): Path<EncodedId>,
+    Validated(payload): Validated<PollDeviceRequest>,
+) -> Result<Json<Option<LightingSchedule>>, Error> {
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
+    if device.id != id {
+        return Err(Error::DeviceNotFound);
+    }
+
+    Device::poll(&state.pool, device.id, payload.uptime_seconds).await?;
+
+    let schedule = match device.profile_id {
+        Some(profile_id) => {
+            let profile = Profile::get_by_id(&state.pool, profile_id).await?;
+            Some(profile.calculate(SCHEDULE_POINTS, Duration::hours(1))?)
+        }
+        None => None,
+    };
+
+    Ok(Json(schedule))
+}
+
 /// Delete a device
 ///
 /// Owner or User may delete **only** their own devices.
@@ -222,9 +370,10 @@ pub async fn regenerate_key(
 pub async fn delete(
     State(state): State<AppState>,
     user: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<StatusCode, Error> {
-    Device::delete(&state.pool, id, |device| match user.role {
+    let id = id.decode().ok_or(Error::DeviceNotFound)?;
+    Device::delete(&state.pool, user.id, user.role, id, |device| match user.role {
         Role::Admin => Ok(true),
         Role::Owner | Role::User(_) if device.owner_id == user.id => Ok(true),
         // Role::User(parent_id) if device.owner_id == parent_id && device.is_public => Ok(true),
@@ -0,0 +1,20 @@
+/// Transport used to nudge a device into pulling a fresh `LightingSchedule`
+/// instead of waiting for its cached one to lapse. Kept synchronous so it
+/// stays object-safe behind a plain `dyn PushTransport`; a real implementation
+/// (FCM, a webhook, ...) is expected to hand off any network I/O itself
+/// (e.g. via `tokio::spawn`) rather than block the caller.
+pub trait PushTransport: Send + Sync {
+    /// Best-effort notification that the device's schedule changed.
+    /// Delivery failures are not surfaced to the caller.
+    fn notify_schedule_changed(&self, push_token: &str);
+}
+
+/// Default transport: logs the notification instead of delivering it.
+/// Stands in until a real transport (FCM/webhook/etc.) is configured.
+pub struct LogPushTransport;
+
+impl PushTransport for LogPushTransport {
+    fn notify_schedule_changed(&self, push_token: &str) {
+        tracing::debug!(push_token, "would push schedule-changed notification");
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use sqlx::{
+    PgConnection,
+    PgPool,
+    Type,
+};
+use utoipa::ToSchema;
+
+use crate::{
+    errors::Error,
+    features::auth::Role,
+};
+
+/// Generates a random 10-character invite code
+fn generate_code() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect()
+}
+
+// Deliberately SHA-256 rather than the Argon2 hasher used for passwords, same
+// reasoning as sessions' refresh-token hashing: invite codes are high-entropy
+// random secrets, not low-entropy user input, so a slow KDF buys nothing and
+// would make `code_hash = $1` lookups impossible without scanning every row.
+fn hash_code(code: &str) -> String {
+    let digest = Sha256::digest(code.as_bytes());
+    format!("{digest:x}")
+}
+
+/// The role an invite grants; distinct from [`Role`] since it carries no
+/// parent id of its own (the parent, when applicable, is the invite's creator)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+pub enum GrantedRole {
+    Admin,
+    Owner,
+    User,
+}
+
+pub struct Invite {
+    pub code_hash: String,
+    pub created_by: i64,
+    pub granted_role: GrantedRole,
+    pub max_uses: i32,
+    pub used_count: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An invite freshly minted by [`Invite::create`], carrying the plaintext
+/// code; only its hash is ever persisted, so this is the one time it exists
+/// outside the inviter's hands
+pub struct IssuedInvite {
+    pub code: String,
+    pub granted_role: GrantedRole,
+    pub max_uses: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Invite {
+    /// Mints a new invite code, good for `max_uses` registrations before it expires
+    pub async fn create(
+        pool: &PgPool,
+        created_by: i64,
+        granted_role: GrantedRole,
+        max_uses: i32,
+        ttl: Duration,
+    ) -> Result<IssuedInvite, Error> {
+        let code = generate_code();
+        let expires_at = Utc::now() + ttl;
+
+        sqlx::query!(
+            r#"INSERT INTO invites (code_hash, created_by, granted_role, max_uses, expires_at)
+               VALUES ($1, $2, $3, $4, $5)"#,
+            hash_code(&code),
+            created_by,
+            granted_role as GrantedRole,
+            max_uses,
+            expires_at
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(IssuedInvite {
+            code,
+            granted_role,
+            max_uses,
+            expires_at,
+        })
+    }
+
+    /// Validates `code` and claims one use of it, on the connection `conn` belongs
+    /// to; the caller is expected to create the registrant in the same transaction
+    pub async fn redeem(conn: &mut PgConnection, code: &str) -> Result<Self, Error> {
+        let invite = sqlx::query_as!(
+            Self,
+            r#"SELECT code_hash, created_by, granted_role AS "granted_role: GrantedRole",
+                      max_uses, used_count, expires_at, created_at
+               FROM invites WHERE code_hash = $1 FOR UPDATE"#,
+            hash_code(code)
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(Error::InviteNotFound)?;
+
+        if invite.expires_at < Utc::now() {
+            return Err(Error::InviteExpired);
+        }
+        if invite.used_count >= invite.max_uses {
+            return Err(Error::InviteExhausted);
+        }
+
+        sqlx::query!(
+            "UPDATE invites SET used_count = used_count + 1 WHERE code_hash = $1",
+            invite.code_hash
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(invite)
+    }
+
+    /// The role (and, for `user`, the parent) a registrant using this invite receives
+    pub fn role(&self) -> Role {
+        match self.granted_role {
+            GrantedRole::Admin => Role::Admin,
+            GrantedRole::Owner => Role::Owner,
+            GrantedRole::User => Role::User(self.created_by),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+};
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use garde::Validate;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use utoipa::ToSchema;
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    extractors::Validated,
+    features::auth::{
+        Authenticated,
+        Role,
+    },
+    responses::CreateInvite,
+};
+
+mod db;
+
+pub use db::{
+    GrantedRole,
+    Invite,
+    IssuedInvite,
+};
+
+pub const TAG: &str = "Invites";
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new().routes(routes!(create))
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CreateInviteRequest {
+    /// Role granted to whoever redeems this code
+    #[garde(skip)]
+    pub granted_role: GrantedRole,
+    /// How many times this code can be redeemed
+    #[garde(range(min = 1))]
+    #[schema(minimum = 1, example = 1)]
+    pub max_uses: i32,
+    /// Hours until this code expires
+    #[garde(range(min = 1))]
+    #[schema(minimum = 1, example = 72)]
+    pub ttl_hours: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct InviteResponse {
+    pub code: String,
+    pub granted_role: GrantedRole,
+    pub max_uses: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Mint a new invite code
+///
+/// Only admins and owners can create invites. The granted role decides what
+/// a registrant redeeming this code becomes; a granted role of `user` makes
+/// the registrant a child of the inviting account, same as open registration's
+/// implicit downgrade.
+#[utoipa::path(
+    post,
+    path = "",
+    request_body = CreateInviteRequest,
+    responses(CreateInvite),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn create(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Validated(payload): Validated<CreateInviteRequest>,
+) -> Result<(StatusCode, Json<InviteResponse>), Error> {
+    match auth.role {
+        Role::Admin | Role::Owner => {}
+        Role::User(_) => return Err(Error::UsersCannotCreateUsers),
+    }
+
+    let invite = Invite::create(
+        &state.pool,
+        auth.id,
+        payload.granted_role,
+        payload.max_uses,
+        Duration::hours(payload.ttl_hours),
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InviteResponse {
+            code: invite.code,
+            granted_role: invite.granted_role,
+            max_uses: invite.max_uses,
+            expires_at: invite.expires_at,
+        }),
+    ))
+}
@@ -0,0 +1,99 @@
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use sqlx::PgPool;
+
+use crate::errors::Error;
+
+/// How long a reset token stays valid for
+const TTL: Duration = Duration::hours(1);
+
+fn hash_token(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    format!("{digest:x}")
+}
+
+fn generate_token() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+pub struct PasswordReset {
+    pub token_hash: String,
+    pub user_id: i64,
+    pub expires_at: DateTime<Utc>,
+    pub used: bool,
+}
+
+impl PasswordReset {
+    /// Invalidates outstanding tokens for the user and issues a fresh one,
+    /// returning the plaintext token to be emailed
+    pub async fn create(pool: &PgPool, user_id: i64) -> Result<String, Error> {
+        let token = generate_token();
+
+        let mut tx = pool.begin().await?;
+
+        sqlx::query!(
+            "UPDATE password_resets SET used = true WHERE user_id = $1 AND used = false",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO password_resets (token_hash, user_id, expires_at)
+             VALUES ($1, $2, $3)",
+            hash_token(&token),
+            user_id,
+            Utc::now() + TTL
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(token)
+    }
+
+    /// Consumes a reset token, returning the associated user id
+    pub async fn consume(pool: &PgPool, token: &str) -> Result<i64, Error> {
+        let mut tx = pool.begin().await?;
+
+        let reset = sqlx::query_as!(
+            Self,
+            "SELECT * FROM password_resets WHERE token_hash = $1 FOR UPDATE",
+            hash_token(token)
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::ResetTokenInvalid)?;
+
+        if reset.used || reset.expires_at < Utc::now() {
+            return Err(Error::ResetTokenExpired);
+        }
+
+        sqlx::query!(
+            "UPDATE password_resets SET used = true WHERE user_id = $1",
+            reset.user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(reset.user_id)
+    }
+}
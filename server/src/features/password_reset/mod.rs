@@ -0,0 +1,118 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+};
+use garde::Validate;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    extractors::Validated,
+    features::auth::{
+        Session,
+        User,
+    },
+    responses::{
+        RequestPasswordReset,
+        ResetPassword,
+    },
+};
+
+mod db;
+
+use db::PasswordReset;
+
+pub const TAG: &str = "Password Reset";
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(reset_request))
+        .routes(routes!(reset))
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct ResetRequest {
+    #[garde(alphanumeric, length(chars, min = 1))]
+    #[schema(min_length = 1, example = "john")]
+    pub username: String,
+}
+
+/// Request a password reset email
+///
+/// Always returns `200`, whether or not the username exists, to avoid
+/// leaking which usernames are registered.
+#[utoipa::path(
+    post,
+    path = "/reset-request",
+    request_body = ResetRequest,
+    responses(RequestPasswordReset),
+    tag = TAG
+)]
+pub async fn reset_request(
+    State(state): State<AppState>,
+    Validated(payload): Validated<ResetRequest>,
+) -> Result<StatusCode, Error> {
+    if let Ok(user) = User::get_by_username(&state.pool, &payload.username).await {
+        let token = PasswordReset::create(&state.pool, user.id).await?;
+        let link = format!("https://lumirum.example/password/reset?token={token}");
+
+        if let Err(err) = state
+            .mailer
+            .send(
+                &user.username,
+                "Reset your LumiRum password",
+                format!("Use this link to reset your password:\n{link}\n\nIf you didn't request this, you can ignore this email."),
+            )
+            .await
+        {
+            tracing::error!("failed to send password reset email: {err}");
+        }
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct Reset {
+    #[garde(length(chars, min = 1))]
+    pub token: String,
+    #[garde(length(chars, min = 8))]
+    #[schema(min_length = 8, example = "lumirum!changed")]
+    pub new_password: String,
+}
+
+/// Complete a password reset
+#[utoipa::path(
+    post,
+    path = "/reset",
+    request_body = Reset,
+    responses(ResetPassword),
+    tag = TAG
+)]
+pub async fn reset(
+    State(state): State<AppState>,
+    Validated(payload): Validated<Reset>,
+) -> Result<StatusCode, Error> {
+    let user_id = PasswordReset::consume(&state.pool, &payload.token).await?;
+    let role = User::get_by_id(&state.pool, user_id).await?.role;
+
+    let password_hash = state.argon2.hash(&payload.new_password)?;
+
+    User::update(&state.pool, user_id, role, user_id, |user| {
+        user.password_hash = password_hash;
+        Ok(true)
+    })
+    .await?;
+
+    // A forgotten-password reset is also the main path a stolen session gets
+    // cut off through, so treat it the same as reuse-detected refresh tokens
+    Session::revoke_all(&state.pool, user_id).await?;
+
+    Ok(StatusCode::OK)
+}
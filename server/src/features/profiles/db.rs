@@ -2,7 +2,9 @@ use chrono::{
     DateTime,
     NaiveTime,
     Utc,
+    Weekday,
 };
+use chrono_tz::Tz;
 use garde::{
     Valid,
     Validate,
@@ -11,16 +13,40 @@ use serde::{
     Deserialize,
     Serialize,
 };
-use sqlx::PgPool;
+use sqlx::{
+    PgPool,
+    types::Json,
+};
 use utoipa::ToSchema;
 
-use crate::errors::Error;
+use crate::{
+    errors::Error,
+    features::{
+        audit,
+        auth::Role,
+    },
+};
+
+/// A per-weekday override of a profile's default sleep window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct WeekdayOverride {
+    #[schema(value_type = String, example = "saturday")]
+    pub weekday: Weekday,
+    #[schema(value_type = String, example = "23:30:00")]
+    pub sleep_start: NaiveTime,
+    #[schema(value_type = String, example = "09:00:00")]
+    pub sleep_end: NaiveTime,
+}
 
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct Profile {
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub id: i64,
     pub name: String,
 
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub owner_id: i64,
     /// Whether the sub-users will see the profile
     pub is_shared: bool,
@@ -28,7 +54,7 @@ pub struct Profile {
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
 
-    pub timezone: String, // TODO: chrono-tz
+    pub timezone: String,
 
     #[schema(value_type = String, example = "22:00:00")]
     pub sleep_start: NaiveTime,
@@ -41,6 +67,18 @@ pub struct Profile {
     pub motion_timeout_seconds: i32,
 
     pub created_at: DateTime<Utc>,
+
+    /// Per-weekday sleep window overrides, e.g. sleeping in on weekends
+    #[schema(value_type = Vec<WeekdayOverride>)]
+    pub weekday_overrides: Json<Vec<WeekdayOverride>>,
+
+    /// Daily budget of minutes at or near `max_color_temp`; the daylight phase is
+    /// extended when the natural schedule falls short of it, but only up to the
+    /// pre-sleep wind-down and a 15-minute morning ramp, so the budget can go unmet
+    pub min_daylight_minutes: i32,
+
+    /// When this profile was soft-deleted, if at all
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -58,6 +96,7 @@ pub struct CreateProfile {
     #[schema(default = true)]
     pub is_shared: bool,
 
+    #[garde(custom(valid_timezone))]
     #[schema(default = "UTC", example = "Europe/Kyiv")]
     pub timezone: String,
 
@@ -79,23 +118,84 @@ pub struct CreateProfile {
 
     #[schema(default = 300)]
     pub motion_timeout_seconds: i32,
+
+    /// Per-weekday sleep window overrides, e.g. sleeping in on weekends
+    #[serde(default)]
+    pub weekday_overrides: Vec<WeekdayOverride>,
+
+    /// Daily budget of minutes at or near `max_color_temp`; the daylight phase is
+    /// extended when the natural schedule falls short of it, but only up to the
+    /// pre-sleep wind-down and a 15-minute morning ramp, so the budget can go unmet
+    #[garde(range(min = 0, max = 1440))]
+    #[schema(default = 360, minimum = 0, maximum = 1440)]
+    pub min_daylight_minutes: i32,
 }
 
-impl Profile {
-    pub async fn create(
-        pool: &PgPool,
-        owner_id: i64,
-        data: Valid<CreateProfile>,
-    ) -> Result<Self, Error> {
+/// Rejects anything that isn't a valid IANA timezone name (e.g. `"Mars/Olympus"`),
+/// so a bad value is caught at the `Validated` extractor boundary instead of
+/// only surfacing once something tries to schedule against it
+fn valid_timezone(value: &str, _context: &()) -> garde::Result {
+    value
+        .parse::<Tz>()
+        .map(|_| ())
+        .map_err(|_| garde::Error::new("not a valid IANA timezone"))
+}
+
+/// Persistence surface for [`Profile`], extracted so a non-Postgres backend
+/// (SQLite, an in-memory store for tests, ...) can be plugged in instead of
+/// this crate hard-coding `PgPool` everywhere. The Postgres implementation
+/// lives behind the `postgres` feature; [`Profile`]'s inherent methods are
+/// thin generic wrappers so existing call sites (already passing `&state.pool`)
+/// don't need to change.
+// TODO: AppState still carries a concrete PgPool rather than `impl ProfileStore`;
+// making it fully generic needs every feature module's State<AppState> to thread
+// the backend type through, which is a bigger refactor than this one.
+#[allow(async_fn_in_trait)]
+pub trait ProfileStore {
+    async fn create(&self, owner_id: i64, data: Valid<CreateProfile>) -> Result<Profile, Error>;
+    async fn get_by_id(&self, id: i64) -> Result<Profile, Error>;
+    async fn list_as_owner(&self, owner_id: i64) -> Result<Vec<Profile>, Error>;
+    async fn list_as_user(&self, user_id: i64, parent_id: i64) -> Result<Vec<Profile>, Error>;
+    async fn update<F>(
+        &self,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<Profile, Error>
+    where
+        F: FnOnce(&mut Profile) -> Result<bool, Error>;
+    /// Soft-deletes the profile by setting `deleted_at`, so it disappears
+    /// from `get_by_id`/`list_*` without losing the row
+    async fn delete<F>(
+        &self,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Profile) -> Result<bool, Error>;
+    /// Clears `deleted_at` on a soft-deleted profile
+    async fn restore(&self, actor_user_id: i64, actor_role: Role, id: i64)
+    -> Result<Profile, Error>;
+    /// Permanently removes a soft-deleted profile's row
+    async fn purge(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error>;
+}
+
+#[cfg(feature = "postgres")]
+impl ProfileStore for PgPool {
+    async fn create(&self, owner_id: i64, data: Valid<CreateProfile>) -> Result<Profile, Error> {
         let profile = sqlx::query_as!(
-            Self,
+            Profile,
             r#"
             INSERT INTO profiles (
                 owner_id, name, latitude, longitude, timezone,
                 sleep_start, sleep_end, night_mode_enabled,
-                min_color_temp, max_color_temp, motion_timeout_seconds
+                min_color_temp, max_color_temp, motion_timeout_seconds,
+                weekday_overrides, min_daylight_minutes
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
             RETURNING *
             "#,
             owner_id,
@@ -108,64 +208,67 @@ impl Profile {
             data.night_mode_enabled,
             data.min_color_temp,
             data.max_color_temp,
-            data.motion_timeout_seconds
+            data.motion_timeout_seconds,
+            Json(&data.weekday_overrides) as _,
+            data.min_daylight_minutes
         )
-        .fetch_one(pool)
+        .fetch_one(self)
         .await?;
 
         Ok(profile)
     }
 
-    pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Self, Error> {
-        sqlx::query_as!(Self, "SELECT * FROM profiles WHERE id = $1", id)
-            .fetch_optional(pool)
-            .await?
-            .ok_or(Error::ProfileNotFound)
+    async fn get_by_id(&self, id: i64) -> Result<Profile, Error> {
+        sqlx::query_as!(
+            Profile,
+            "SELECT * FROM profiles WHERE id = $1 AND deleted_at IS NULL",
+            id
+        )
+        .fetch_optional(self)
+        .await?
+        .ok_or(Error::ProfileNotFound)
     }
 
-    pub async fn list_as_owner(pool: &PgPool, owner_id: i64) -> Result<Vec<Self>, Error> {
+    async fn list_as_owner(&self, owner_id: i64) -> Result<Vec<Profile>, Error> {
         Ok(sqlx::query_as!(
-            Self,
-            "SELECT * FROM profiles WHERE owner_id = $1 OR (
-                owner_id IN (SELECT id FROM users WHERE parent_id = $1)
+            Profile,
+            "SELECT * FROM profiles WHERE (owner_id = $1 OR (
+                owner_id IN (SELECT id FROM users WHERE parent_id = $1 AND deleted_at IS NULL)
                 AND is_shared = true
-             ) ORDER BY created_at DESC",
+             )) AND deleted_at IS NULL ORDER BY created_at DESC",
             owner_id
         )
-        .fetch_all(pool)
+        .fetch_all(self)
         .await?)
     }
-    pub async fn list_as_user(
-        pool: &PgPool,
-        user_id: i64,
-        parent_id: i64,
-    ) -> Result<Vec<Self>, Error> {
+
+    async fn list_as_user(&self, user_id: i64, parent_id: i64) -> Result<Vec<Profile>, Error> {
         Ok(sqlx::query_as!(
-            Self,
-            "SELECT * FROM profiles WHERE owner_id = $1 OR (
+            Profile,
+            "SELECT * FROM profiles WHERE (owner_id = $1 OR (
                 owner_id = $2 AND is_shared = true
-             ) ORDER BY created_at DESC",
+             )) AND deleted_at IS NULL ORDER BY created_at DESC",
             user_id,
             parent_id,
         )
-        .fetch_all(pool)
+        .fetch_all(self)
         .await?)
     }
 
-    async fn get_by_id_for_update(conn: &mut sqlx::PgConnection, id: i64) -> Result<Self, Error> {
-        sqlx::query_as!(Self, "SELECT * FROM profiles WHERE id = $1 FOR UPDATE", id)
-            .fetch_optional(conn)
-            .await?
-            .ok_or(Error::ProfileNotFound)
-    }
-
-    pub async fn update<F>(pool: &PgPool, id: i64, func: F) -> Result<Self, Error>
+    async fn update<F>(
+        &self,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<Profile, Error>
     where
-        F: FnOnce(&mut Self) -> Result<bool, Error>,
+        F: FnOnce(&mut Profile) -> Result<bool, Error>,
     {
-        let mut tx = pool.begin().await?;
+        let mut tx = self.begin().await?;
 
-        let mut profile = Self::get_by_id_for_update(&mut tx, id).await?;
+        let before = get_by_id_for_update(&mut tx, id).await?;
+        let mut profile = before.clone();
 
         let updated = func(&mut profile)?;
 
@@ -175,7 +278,7 @@ impl Profile {
 
         // TODO: Validation?
         let profile = sqlx::query_as!(
-            Self,
+            Profile,
             r#"
             UPDATE profiles
             SET
@@ -188,8 +291,10 @@ impl Profile {
                 night_mode_enabled = $7,
                 min_color_temp = $8,
                 max_color_temp = $9,
-                motion_timeout_seconds = $10
-            WHERE id = $11
+                motion_timeout_seconds = $10,
+                weekday_overrides = $11,
+                min_daylight_minutes = $12
+            WHERE id = $13
             RETURNING *
             "#,
             profile.name,
@@ -202,23 +307,45 @@ impl Profile {
             profile.min_color_temp,
             profile.max_color_temp,
             profile.motion_timeout_seconds,
+            profile.weekday_overrides as _,
+            profile.min_daylight_minutes,
             profile.id
         )
         .fetch_one(&mut *tx)
         .await?;
 
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "update",
+            "profile",
+            profile.id,
+            audit::diff(
+                &serde_json::to_value(&before).expect("Profile always serializes to JSON"),
+                &serde_json::to_value(&profile).expect("Profile always serializes to JSON"),
+            ),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(profile)
     }
 
-    pub async fn delete<F>(pool: &PgPool, id: i64, func: F) -> Result<(), Error>
+    async fn delete<F>(
+        &self,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<(), Error>
     where
-        F: FnOnce(&mut Self) -> Result<bool, Error>,
+        F: FnOnce(&mut Profile) -> Result<bool, Error>,
     {
-        let mut tx = pool.begin().await?;
+        let mut tx = self.begin().await?;
 
-        let mut profile = Self::get_by_id_for_update(&mut tx, id).await?;
+        let mut profile = get_by_id_for_update(&mut tx, id).await?;
 
         let delete = func(&mut profile)?;
 
@@ -226,14 +353,186 @@ impl Profile {
             return Ok(());
         }
 
-        sqlx::query!("DELETE FROM profiles WHERE id = $1", id)
+        sqlx::query!("UPDATE profiles SET deleted_at = now() WHERE id = $1", id)
             .execute(&mut *tx)
             .await?;
 
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "delete",
+            "profile",
+            profile.id,
+            serde_json::to_value(&profile).expect("Profile always serializes to JSON"),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn restore(
+        &self,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<Profile, Error> {
+        let mut tx = self.begin().await?;
+
+        let profile = sqlx::query_as!(
+            Profile,
+            "UPDATE profiles SET deleted_at = NULL
+             WHERE id = $1 AND deleted_at IS NOT NULL
+             RETURNING *",
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(Error::ProfileNotFound)?;
+
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "restore",
+            "profile",
+            profile.id,
+            serde_json::to_value(&profile).expect("Profile always serializes to JSON"),
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(profile)
+    }
+
+    async fn purge(&self, actor_user_id: i64, actor_role: Role, id: i64) -> Result<(), Error> {
+        let mut tx = self.begin().await?;
+
+        let rows_affected = sqlx::query!(
+            "DELETE FROM profiles WHERE id = $1 AND deleted_at IS NOT NULL",
+            id
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(Error::ProfileNotFound);
+        }
+
+        audit::record(
+            &mut *tx,
+            actor_user_id,
+            actor_role,
+            "purge",
+            "profile",
+            id,
+            serde_json::json!({}),
+        )
+        .await?;
+
         tx.commit().await?;
 
         Ok(())
     }
+}
+
+#[cfg(feature = "postgres")]
+async fn get_by_id_for_update(
+    conn: &mut sqlx::PgConnection,
+    id: i64,
+) -> Result<Profile, Error> {
+    sqlx::query_as!(
+        Profile,
+        "SELECT * FROM profiles WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        id
+    )
+    .fetch_optional(conn)
+    .await?
+    .ok_or(Error::ProfileNotFound)
+}
+
+impl Profile {
+    pub async fn create(
+        store: &impl ProfileStore,
+        owner_id: i64,
+        data: Valid<CreateProfile>,
+    ) -> Result<Self, Error> {
+        store.create(owner_id, data).await
+    }
+
+    pub async fn get_by_id(store: &impl ProfileStore, id: i64) -> Result<Self, Error> {
+        store.get_by_id(id).await
+    }
+
+    pub async fn list_as_owner(store: &impl ProfileStore, owner_id: i64) -> Result<Vec<Self>, Error> {
+        store.list_as_owner(owner_id).await
+    }
+
+    pub async fn list_as_user(
+        store: &impl ProfileStore,
+        user_id: i64,
+        parent_id: i64,
+    ) -> Result<Vec<Self>, Error> {
+        store.list_as_user(user_id, parent_id).await
+    }
+
+    pub async fn update<F>(
+        store: &impl ProfileStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<bool, Error>,
+    {
+        store.update(actor_user_id, actor_role, id, func).await
+    }
+
+    pub async fn delete<F>(
+        store: &impl ProfileStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+        func: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(&mut Self) -> Result<bool, Error>,
+    {
+        store.delete(actor_user_id, actor_role, id, func).await
+    }
+
+    /// Un-does a previous [`Profile::delete`]
+    pub async fn restore(
+        store: &impl ProfileStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<Self, Error> {
+        store.restore(actor_user_id, actor_role, id).await
+    }
+
+    /// Permanently removes an already soft-deleted profile
+    pub async fn purge(
+        store: &impl ProfileStore,
+        actor_user_id: i64,
+        actor_role: Role,
+        id: i64,
+    ) -> Result<(), Error> {
+        store.purge(actor_user_id, actor_role, id).await
+    }
+
+    /// Parses `timezone` into a [`Tz`], so scheduling can do correct local-time
+    /// conversions (including DST) instead of juggling the raw string. Infallible
+    /// in practice since `valid_timezone` rejects bad values at the `Validated`
+    /// extractor boundary before a `Profile` is ever created with one.
+    pub fn tz(&self) -> Result<Tz, Error> {
+        Ok(self.timezone.parse()?)
+    }
 
     pub fn patch(&mut self, new: CreateProfile) -> bool {
         // TODO: this begs for refactor
@@ -284,6 +583,14 @@ impl Profile {
             self.motion_timeout_seconds = new.motion_timeout_seconds;
             updated = true;
         }
+        if self.weekday_overrides.0 != new.weekday_overrides {
+            self.weekday_overrides = Json(new.weekday_overrides);
+            updated = true;
+        }
+        if self.min_daylight_minutes != new.min_daylight_minutes {
+            self.min_daylight_minutes = new.min_daylight_minutes;
+            updated = true;
+        }
 
         updated
     }
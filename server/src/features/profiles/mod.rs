@@ -13,19 +13,25 @@ use utoipa_axum::{
 
 use crate::{
     AppState,
+    encoded_id::EncodedId,
     errors::Error,
     extractors::Validated,
-    features::auth::{
-        Authenticated,
-        Role,
-        User,
+    features::{
+        auth::{
+            Authenticated,
+            Role,
+            User,
+        },
+        devices,
     },
     responses::{
         DeleteProfile,
         GetProfile,
         GetProfiles,
         PostProfile,
+        PurgeProfile,
         PutProfile,
+        RestoreProfile,
     },
 };
 
@@ -40,6 +46,7 @@ pub fn router() -> OpenApiRouter<AppState> {
     OpenApiRouter::new()
         .routes(routes!(post, get_all))
         .routes(routes!(get, put, delete))
+        .routes(routes!(restore, purge))
 }
 
 /// Get profile info
@@ -57,8 +64,9 @@ pub fn router() -> OpenApiRouter<AppState> {
 pub async fn get(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<Json<Profile>, Error> {
+    let id = id.decode().ok_or(Error::ProfileNotFound)?;
     let profile = Profile::get_by_id(&state.pool, id).await?;
 
     Ok(Json(match auth.role {
@@ -129,13 +137,14 @@ pub async fn post(
 pub async fn put(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
     Validated(payload): Validated<CreateProfile>,
 ) -> Result<Json<Profile>, Error> {
+    let id = id.decode().ok_or(Error::ProfileNotFound)?;
     let children = User::get_children(&state.pool, auth.id).await?;
 
     let payload = payload.into_inner();
-    let profile = Profile::update(&state.pool, id, |profile| match auth.role {
+    let profile = Profile::update(&state.pool, auth.id, auth.role, id, |profile| match auth.role {
         Role::Admin => Ok(profile.patch(payload)),
         Role::Owner | Role::User(_) if profile.owner_id == auth.id => Ok(profile.patch(payload)),
         Role::User(parent) if profile.owner_id == parent && profile.is_shared => {
@@ -154,6 +163,10 @@ pub async fn put(
     })
     .await?;
 
+    // Already-provisioned devices are otherwise stuck on a stale curve until
+    // their cached schedule's valid_until lapses
+    devices::notify_profile_changed(&state, profile.id).await?;
+
     Ok(Json(profile))
 }
 
@@ -170,9 +183,10 @@ pub async fn put(
 pub async fn delete(
     State(state): State<AppState>,
     user: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<StatusCode, Error> {
-    Profile::delete(&state.pool, id, |profile| match user.role {
+    let id = id.decode().ok_or(Error::ProfileNotFound)?;
+    Profile::delete(&state.pool, user.id, user.role, id, |profile| match user.role {
         Role::Admin => Ok(true),
         Role::Owner | Role::User(_) if profile.owner_id == user.id => Ok(true),
         _ => Err(Error::ProfileNotFound),
@@ -181,3 +195,53 @@ pub async fn delete(
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Restore a soft-deleted profile
+///
+/// Admin-only.
+#[utoipa::path(
+    post,
+    path = "/{id}/restore",
+    responses(RestoreProfile),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn restore(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(id): Path<EncodedId>,
+) -> Result<Json<Profile>, Error> {
+    if !matches!(auth.role, Role::Admin) {
+        return Err(Error::ProfileNotFound);
+    }
+
+    let id = id.decode().ok_or(Error::ProfileNotFound)?;
+
+    Ok(Json(Profile::restore(&state.pool, auth.id, auth.role, id).await?))
+}
+
+/// Permanently delete a soft-deleted profile
+///
+/// Admin-only.
+#[utoipa::path(
+    delete,
+    path = "/{id}/purge",
+    responses(PurgeProfile),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn purge(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(id): Path<EncodedId>,
+) -> Result<StatusCode, Error> {
+    if !matches!(auth.role, Role::Admin) {
+        return Err(Error::ProfileNotFound);
+    }
+
+    let id = id.decode().ok_or(Error::ProfileNotFound)?;
+
+    Profile::purge(&state.pool, auth.id, auth.role, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -18,7 +18,9 @@ pub fn router() -> OpenApiRouter<crate::AppState> {
         .nest(
             "/users",
             OpenApiRouter::new()
-                .routes(routes!(users::get, users::delete))
-                .routes(routes!(users::get_all)),
+                .routes(routes!(users::get, users::delete, users::patch))
+                .routes(routes!(users::get_all))
+                .routes(routes!(users::restore, users::purge))
+                .routes(routes!(users::request_delete_token)),
         )
 }
@@ -2,27 +2,61 @@ use axum::{
     Json,
     extract::{
         Path,
+        Query,
         State,
     },
-    http::StatusCode,
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
+};
+use garde::Validate;
+use serde::Deserialize;
+use utoipa::{
+    IntoParams,
+    ToSchema,
 };
 
 use crate::{
     AppState,
     errors::Error,
+    extractors::Validated,
     features::auth::{
         AdminAuthenticated,
+        DeleteTokenResponse,
+        Role,
+        Session,
         User,
+        sign_delete_token,
+        verify_delete_token,
     },
     responses::{
         DeleteMe,
         GetUser,
         GetUsers,
+        PatchUser,
+        PurgeUser,
+        RequestAccountDeletion,
+        RestoreUser,
     },
 };
 
 use super::TAG;
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ListUsersQuery {
+    /// Restrict to one role, e.g. `"admin"`, `"owner"`, `"user"`
+    #[param(example = "owner")]
+    pub role: Option<String>,
+    /// Restrict to blocked or unblocked accounts
+    pub blocked: Option<bool>,
+    /// Max rows to return, capped at 200
+    #[param(example = 50)]
+    pub limit: Option<i64>,
+    #[param(example = 0)]
+    pub offset: Option<i64>,
+}
+
 /// Get user
 #[utoipa::path(
     get,
@@ -40,9 +74,13 @@ pub async fn get(
 }
 
 /// List all users
+///
+/// Supports pagination (`limit`, capped at 200, default 50; `offset`) and
+/// optional filtering by `role` and `blocked` state.
 #[utoipa::path(
     get,
     path = "",
+    params(ListUsersQuery),
     responses(GetUsers),
     tag = TAG,
     security(("jwt" = []))
@@ -50,11 +88,96 @@ pub async fn get(
 pub async fn get_all(
     State(state): State<AppState>,
     AdminAuthenticated(_auth): AdminAuthenticated,
+    Query(query): Query<ListUsersQuery>,
 ) -> Result<Json<Vec<User>>, Error> {
-    User::get_all(&state.pool).await.map(Json)
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    User::list(&state.pool, query.role, query.blocked, limit, offset)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct PatchUserRequest {
+    /// Reassign the account's role (and, for `user`, its parent)
+    #[garde(skip)]
+    pub role: Option<Role>,
+    /// Block or unblock the account; blocking invalidates its sessions
+    #[garde(skip)]
+    pub blocked: Option<bool>,
+}
+
+/// Update a user's role and/or blocked state
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    request_body = PatchUserRequest,
+    responses(PatchUser),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn patch(
+    State(state): State<AppState>,
+    AdminAuthenticated(auth): AdminAuthenticated,
+    Path(id): Path<i64>,
+    Validated(payload): Validated<PatchUserRequest>,
+) -> Result<Json<User>, Error> {
+    let payload = payload.into_inner();
+    let blocking = payload.blocked == Some(true);
+
+    let user = User::update(&state.pool, auth.id, auth.role, id, |user| {
+        let mut changed = false;
+
+        if let Some(role) = payload.role {
+            user.role = role;
+            changed = true;
+        }
+        if let Some(blocked) = payload.blocked {
+            user.blocked = blocked;
+            changed = true;
+        }
+
+        Ok(changed)
+    })
+    .await?;
+
+    // Cutting off a still-unexpired JWT immediately, same as the blocked
+    // check at login/request time, not just preventing future logins
+    if blocking {
+        Session::revoke_all(&state.pool, id).await?;
+    }
+
+    Ok(Json(user))
+}
+
+/// Request a deletion confirmation token for a user
+///
+/// Mints a 5-minute token scoped to `id`, which `DELETE /{id}` then requires
+/// via the `x-delete-token` header, so the destructive call can't be fired
+/// by itself (accidentally or via CSRF).
+#[utoipa::path(
+    post,
+    path = "/{id}/delete-token",
+    responses(RequestAccountDeletion),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn request_delete_token(
+    State(state): State<AppState>,
+    AdminAuthenticated(_auth): AdminAuthenticated,
+    Path(id): Path<i64>,
+) -> Result<Json<DeleteTokenResponse>, Error> {
+    let user = User::get_by_id(&state.pool, id).await?;
+    let delete_token = sign_delete_token(user.id, user.role, &state.jwt)?;
+
+    Ok(Json(DeleteTokenResponse { delete_token }))
 }
 
 /// Delete a user
+///
+/// Requires a deletion token from [`request_delete_token`] in the
+/// `x-delete-token` header, scoped to the target `id`.
 #[utoipa::path(
     delete,
     path = "/{id}",
@@ -66,12 +189,54 @@ pub async fn delete(
     State(state): State<AppState>,
     AdminAuthenticated(auth): AdminAuthenticated,
     Path(id): Path<i64>,
+    headers: HeaderMap,
 ) -> Result<StatusCode, Error> {
     if auth.id == id {
         return Err(Error::CannotDeleteAnAdmin);
     }
 
-    User::delete(&state.pool, id).await?;
+    let delete_token = headers
+        .get("x-delete-token")
+        .ok_or(Error::MissingCredentials)?
+        .to_str()
+        .map_err(|_| Error::InvalidToken)?;
+    verify_delete_token(delete_token, id, &state.jwt)?;
+
+    User::delete(&state.pool, auth.id, auth.role, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a soft-deleted user
+#[utoipa::path(
+    post,
+    path = "/{id}/restore",
+    responses(RestoreUser),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn restore(
+    State(state): State<AppState>,
+    AdminAuthenticated(auth): AdminAuthenticated,
+    Path(id): Path<i64>,
+) -> Result<Json<User>, Error> {
+    Ok(Json(User::restore(&state.pool, auth.id, auth.role, id).await?))
+}
+
+/// Permanently delete a soft-deleted user
+#[utoipa::path(
+    delete,
+    path = "/{id}/purge",
+    responses(PurgeUser),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn purge(
+    State(state): State<AppState>,
+    AdminAuthenticated(auth): AdminAuthenticated,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, Error> {
+    User::purge(&state.pool, auth.id, auth.role, id).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -1,5 +1,6 @@
 use chrono::{
     DateTime,
+    Duration,
     Utc,
 };
 use garde::{
@@ -17,7 +18,11 @@ use crate::errors::Error;
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct Telemetry {
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub id: i64,
+    #[serde(serialize_with = "crate::encoded_id::serialize")]
+    #[schema(value_type = String, example = "Uk3xQ9")]
     pub device_id: i64,
 
     pub event_type: String,
@@ -28,6 +33,9 @@ pub struct Telemetry {
     pub ambient_light: Option<i16>,
 
     pub created_at: DateTime<Utc>,
+
+    /// When this entry was soft-deleted, if at all
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
@@ -51,15 +59,112 @@ pub struct CreateTelemetry {
     pub ambient_light: Option<i16>,
 }
 
-impl Telemetry {
-    /// Create a new telemetry entry
-    pub async fn create(
-        pool: &PgPool,
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[schema(as = CreateTelemetryBatchRequest)]
+pub struct CreateTelemetryBatch {
+    #[garde(length(min = 1, max = 500), dive)]
+    pub events: Vec<CreateTelemetry>,
+}
+
+/// One time-bucketed rollup of a device's telemetry, as returned by
+/// [`Telemetry::aggregate`]
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TelemetryBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub avg_brightness: Option<f64>,
+    pub avg_color_temp: Option<f64>,
+    pub avg_ambient_light: Option<f64>,
+    pub motion_events: i64,
+}
+
+/// How each bucket's numeric readings are reduced to one point
+#[derive(Debug, Clone, Copy, Default)]
+pub enum AggFunction {
+    #[default]
+    Avg,
+    Min,
+    Max,
+    /// The reading closest to the start of the bucket
+    First,
+    /// The reading closest to the end of the bucket
+    Last,
+}
+
+impl AggFunction {
+    pub fn parse(raw: Option<&str>) -> Result<Self, Error> {
+        Ok(match raw {
+            None | Some("avg") => Self::Avg,
+            Some("min") => Self::Min,
+            Some("max") => Self::Max,
+            Some("first") => Self::First,
+            Some("last") => Self::Last,
+            Some(_) => return Err(Error::InvalidAggFunction),
+        })
+    }
+}
+
+/// Persistence surface for [`Telemetry`], extracted so a non-Postgres backend
+/// can be plugged in instead of this crate hard-coding `PgPool` everywhere.
+/// The Postgres implementation lives behind the `postgres` feature;
+/// [`Telemetry`]'s inherent methods are thin generic wrappers so existing
+/// call sites (already passing `&state.pool`) don't need to change.
+#[allow(async_fn_in_trait)]
+pub trait TelemetryStore {
+    async fn create(
+        &self,
         device_id: i64,
         data: Valid<CreateTelemetry>,
-    ) -> Result<Self, Error> {
+    ) -> Result<Telemetry, Error>;
+    async fn get_by_id(&self, id: i64) -> Result<Telemetry, Error>;
+    async fn list(
+        &self,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Telemetry>, Error>;
+    async fn list_as_owner(
+        &self,
+        owner_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Telemetry>, Error>;
+    async fn list_as_user(
+        &self,
+        user_id: i64,
+        parent_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Telemetry>, Error>;
+    async fn delete(
+        &self,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Error>;
+    async fn create_batch(
+        &self,
+        device_id: i64,
+        data: Valid<CreateTelemetryBatch>,
+    ) -> Result<Vec<Telemetry>, Error>;
+    async fn aggregate(
+        &self,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: Duration,
+        agg: AggFunction,
+    ) -> Result<Vec<TelemetryBucket>, Error>;
+}
+
+#[cfg(feature = "postgres")]
+impl TelemetryStore for PgPool {
+    async fn create(
+        &self,
+        device_id: i64,
+        data: Valid<CreateTelemetry>,
+    ) -> Result<Telemetry, Error> {
         let telemetry = sqlx::query_as!(
-            Self,
+            Telemetry,
             "INSERT INTO telemetry (
                 device_id, event_type, motion_detected, light_is_on, brightness,
                 color_temp, ambient_light
@@ -74,100 +179,361 @@ impl Telemetry {
             data.color_temp,
             data.ambient_light
         )
-        .fetch_one(pool)
+        .fetch_one(self)
         .await?;
 
         Ok(telemetry)
     }
 
-    pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Self, Error> {
-        sqlx::query_as!(Self, "SELECT * FROM telemetry WHERE id = $1", id)
-            .fetch_optional(pool)
-            .await?
-            .ok_or(Error::TelemetryNotFound)
+    async fn get_by_id(&self, id: i64) -> Result<Telemetry, Error> {
+        sqlx::query_as!(
+            Telemetry,
+            "SELECT * FROM telemetry WHERE id = $1 AND deleted_at IS NULL",
+            id
+        )
+        .fetch_optional(self)
+        .await?
+        .ok_or(Error::TelemetryNotFound)
     }
 
-    /// Get telemetry for a specific device
-    pub async fn list(
-        pool: &PgPool,
+    async fn list(
+        &self,
         device_id: i64,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
-    ) -> Result<Vec<Self>, Error> {
+    ) -> Result<Vec<Telemetry>, Error> {
         Ok(sqlx::query_as!(
-            Self,
+            Telemetry,
             "SELECT * FROM telemetry
              WHERE device_id = $1 AND created_at >= $2 AND created_at < $3
+                AND deleted_at IS NULL
              ORDER BY created_at DESC",
             device_id,
             start,
             end
         )
-        .fetch_all(pool)
+        .fetch_all(self)
         .await?)
     }
 
-    /// Get telemetry for owner's devices and their users' public devices
-    pub async fn list_as_owner(
-        pool: &PgPool,
+    async fn list_as_owner(
+        &self,
         owner_id: i64,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
-    ) -> Result<Vec<Self>, Error> {
+    ) -> Result<Vec<Telemetry>, Error> {
         Ok(sqlx::query_as!(
-            Self,
+            Telemetry,
             "SELECT t.* FROM telemetry t
              INNER JOIN devices d ON d.id = t.device_id
              WHERE (d.owner_id = $1 OR (
                 d.owner_id IN (SELECT id FROM users WHERE parent_id = $1) AND d.is_public = true)
              ) AND t.created_at >= $2 AND t.created_at < $3
+                AND t.deleted_at IS NULL
              ORDER BY t.created_at DESC",
             owner_id,
             start,
             end
         )
-        .fetch_all(pool)
+        .fetch_all(self)
         .await?)
     }
 
-    /// Get telemetry for user's devices and their parent's public devices
-    pub async fn list_as_user(
-        pool: &PgPool,
+    async fn list_as_user(
+        &self,
         user_id: i64,
         parent_id: i64,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
-    ) -> Result<Vec<Self>, Error> {
+    ) -> Result<Vec<Telemetry>, Error> {
         Ok(sqlx::query_as!(
-            Self,
+            Telemetry,
             "SELECT t.* FROM telemetry t
              INNER JOIN devices d ON d.id = t.device_id
              WHERE (d.owner_id = $1 OR (d.owner_id = $2 AND d.is_public = true))
                 AND t.created_at >= $3 AND t.created_at < $4
+                AND t.deleted_at IS NULL
              ORDER BY t.created_at DESC",
             user_id,
             parent_id,
             start,
             end
         )
-        .fetch_all(pool)
+        .fetch_all(self)
         .await?)
     }
 
-    pub async fn delete(
-        pool: &PgPool,
+    /// Soft-deletes telemetry in the given range by setting `deleted_at`,
+    /// rather than losing the rows outright
+    async fn delete(
+        &self,
         device_id: i64,
         start: DateTime<Utc>,
         end: DateTime<Utc>,
     ) -> Result<u64, Error> {
         Ok(sqlx::query!(
-            "DELETE FROM telemetry WHERE device_id = $1 AND created_at >= $2 AND created_at < $3",
+            "UPDATE telemetry SET deleted_at = now()
+             WHERE device_id = $1 AND created_at >= $2 AND created_at < $3
+                AND deleted_at IS NULL",
             device_id,
             start,
             end
         )
-        .execute(pool)
+        .execute(self)
         .await?
         .rows_affected())
     }
+
+    async fn create_batch(
+        &self,
+        device_id: i64,
+        data: Valid<CreateTelemetryBatch>,
+    ) -> Result<Vec<Telemetry>, Error> {
+        let data = data.into_inner();
+
+        let event_type: Vec<String> = data.events.iter().map(|e| e.event_type.clone()).collect();
+        let motion_detected: Vec<Option<bool>> =
+            data.events.iter().map(|e| e.motion_detected).collect();
+        let light_is_on: Vec<Option<bool>> = data.events.iter().map(|e| e.light_is_on).collect();
+        let brightness: Vec<Option<i16>> = data.events.iter().map(|e| e.brightness).collect();
+        let color_temp: Vec<Option<i16>> = data.events.iter().map(|e| e.color_temp).collect();
+        let ambient_light: Vec<Option<i16>> =
+            data.events.iter().map(|e| e.ambient_light).collect();
+
+        Ok(sqlx::query_as!(
+            Telemetry,
+            r#"
+            INSERT INTO telemetry (
+                device_id, event_type, motion_detected, light_is_on, brightness,
+                color_temp, ambient_light
+            )
+            SELECT $1, * FROM UNNEST(
+                $2::text[], $3::bool[], $4::bool[], $5::int2[], $6::int2[], $7::int2[]
+            )
+            RETURNING *
+            "#,
+            device_id,
+            &event_type,
+            &motion_detected as &[Option<bool>],
+            &light_is_on as &[Option<bool>],
+            &brightness as &[Option<i16>],
+            &color_temp as &[Option<i16>],
+            &ambient_light as &[Option<i16>],
+        )
+        .fetch_all(self)
+        .await?)
+    }
+
+    /// Buckets a device's telemetry into fixed-width intervals, reducing
+    /// each bucket's numeric readings with `agg` and counting motion events
+    async fn aggregate(
+        &self,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: Duration,
+        agg: AggFunction,
+    ) -> Result<Vec<TelemetryBucket>, Error> {
+        let bucket_interval = format!("{} seconds", bucket.num_seconds());
+
+        Ok(match agg {
+            AggFunction::Avg => {
+                sqlx::query_as!(
+                    TelemetryBucket,
+                    r#"
+                    SELECT
+                        date_bin($1::interval, created_at, timestamptz '1970-01-01') AS "bucket_start!",
+                        avg(brightness) AS avg_brightness,
+                        avg(color_temp) AS avg_color_temp,
+                        avg(ambient_light) AS avg_ambient_light,
+                        count(*) FILTER (WHERE motion_detected) AS "motion_events!"
+                    FROM telemetry
+                    WHERE device_id = $2 AND created_at >= $3 AND created_at < $4
+                        AND deleted_at IS NULL
+                    GROUP BY bucket_start
+                    ORDER BY bucket_start
+                    "#,
+                    bucket_interval,
+                    device_id,
+                    start,
+                    end
+                )
+                .fetch_all(self)
+                .await?
+            }
+            AggFunction::Min => {
+                sqlx::query_as!(
+                    TelemetryBucket,
+                    r#"
+                    SELECT
+                        date_bin($1::interval, created_at, timestamptz '1970-01-01') AS "bucket_start!",
+                        min(brightness)::float8 AS avg_brightness,
+                        min(color_temp)::float8 AS avg_color_temp,
+                        min(ambient_light)::float8 AS avg_ambient_light,
+                        count(*) FILTER (WHERE motion_detected) AS "motion_events!"
+                    FROM telemetry
+                    WHERE device_id = $2 AND created_at >= $3 AND created_at < $4
+                        AND deleted_at IS NULL
+                    GROUP BY bucket_start
+                    ORDER BY bucket_start
+                    "#,
+                    bucket_interval,
+                    device_id,
+                    start,
+                    end
+                )
+                .fetch_all(self)
+                .await?
+            }
+            AggFunction::Max => {
+                sqlx::query_as!(
+                    TelemetryBucket,
+                    r#"
+                    SELECT
+                        date_bin($1::interval, created_at, timestamptz '1970-01-01') AS "bucket_start!",
+                        max(brightness)::float8 AS avg_brightness,
+                        max(color_temp)::float8 AS avg_color_temp,
+                        max(ambient_light)::float8 AS avg_ambient_light,
+                        count(*) FILTER (WHERE motion_detected) AS "motion_events!"
+                    FROM telemetry
+                    WHERE device_id = $2 AND created_at >= $3 AND created_at < $4
+                        AND deleted_at IS NULL
+                    GROUP BY bucket_start
+                    ORDER BY bucket_start
+                    "#,
+                    bucket_interval,
+                    device_id,
+                    start,
+                    end
+                )
+                .fetch_all(self)
+                .await?
+            }
+            AggFunction::First => {
+                sqlx::query_as!(
+                    TelemetryBucket,
+                    r#"
+                    SELECT
+                        date_bin($1::interval, created_at, timestamptz '1970-01-01') AS "bucket_start!",
+                        (array_agg(brightness ORDER BY created_at ASC))[1]::float8 AS avg_brightness,
+                        (array_agg(color_temp ORDER BY created_at ASC))[1]::float8 AS avg_color_temp,
+                        (array_agg(ambient_light ORDER BY created_at ASC))[1]::float8 AS avg_ambient_light,
+                        count(*) FILTER (WHERE motion_detected) AS "motion_events!"
+                    FROM telemetry
+                    WHERE device_id = $2 AND created_at >= $3 AND created_at < $4
+                        AND deleted_at IS NULL
+                    GROUP BY bucket_start
+                    ORDER BY bucket_start
+                    "#,
+                    bucket_interval,
+                    device_id,
+                    start,
+                    end
+                )
+                .fetch_all(self)
+                .await?
+            }
+            AggFunction::Last => {
+                sqlx::query_as!(
+                    TelemetryBucket,
+                    r#"
+                    SELECT
+                        date_bin($1::interval, created_at, timestamptz '1970-01-01') AS "bucket_start!",
+                        (array_agg(brightness ORDER BY created_at DESC))[1]::float8 AS avg_brightness,
+                        (array_agg(color_temp ORDER BY created_at DESC))[1]::float8 AS avg_color_temp,
+                        (array_agg(ambient_light ORDER BY created_at DESC))[1]::float8 AS avg_ambient_light,
+                        count(*) FILTER (WHERE motion_detected) AS "motion_events!"
+                    FROM telemetry
+                    WHERE device_id = $2 AND created_at >= $3 AND created_at < $4
+                        AND deleted_at IS NULL
+                    GROUP BY bucket_start
+                    ORDER BY bucket_start
+                    "#,
+                    bucket_interval,
+                    device_id,
+                    start,
+                    end
+                )
+                .fetch_all(self)
+                .await?
+            }
+        })
+    }
+}
+
+impl Telemetry {
+    /// Create a new telemetry entry
+    pub async fn create(
+        store: &impl TelemetryStore,
+        device_id: i64,
+        data: Valid<CreateTelemetry>,
+    ) -> Result<Self, Error> {
+        store.create(device_id, data).await
+    }
+
+    pub async fn get_by_id(store: &impl TelemetryStore, id: i64) -> Result<Self, Error> {
+        store.get_by_id(id).await
+    }
+
+    /// Get telemetry for a specific device
+    pub async fn list(
+        store: &impl TelemetryStore,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Self>, Error> {
+        store.list(device_id, start, end).await
+    }
+
+    /// Get telemetry for owner's devices and their users' public devices
+    pub async fn list_as_owner(
+        store: &impl TelemetryStore,
+        owner_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Self>, Error> {
+        store.list_as_owner(owner_id, start, end).await
+    }
+
+    /// Get telemetry for user's devices and their parent's public devices
+    pub async fn list_as_user(
+        store: &impl TelemetryStore,
+        user_id: i64,
+        parent_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Self>, Error> {
+        store.list_as_user(user_id, parent_id, start, end).await
+    }
+
+    pub async fn delete(
+        store: &impl TelemetryStore,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<u64, Error> {
+        store.delete(device_id, start, end).await
+    }
+
+    /// Insert many telemetry entries for a device in a single round-trip
+    pub async fn create_batch(
+        store: &impl TelemetryStore,
+        device_id: i64,
+        data: Valid<CreateTelemetryBatch>,
+    ) -> Result<Vec<Self>, Error> {
+        store.create_batch(device_id, data).await
+    }
+
+    /// Time-bucket a device's telemetry into fixed-width intervals, reducing
+    /// each bucket's readings with `agg` (defaults to averaging)
+    pub async fn aggregate(
+        store: &impl TelemetryStore,
+        device_id: i64,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: Duration,
+        agg: AggFunction,
+    ) -> Result<Vec<TelemetryBucket>, Error> {
+        store.aggregate(device_id, start, end, bucket, agg).await
+    }
 }
@@ -9,6 +9,7 @@ use axum::{
 };
 use chrono::{
     DateTime,
+    Duration,
     Utc,
 };
 use serde::Deserialize;
@@ -20,6 +21,7 @@ use utoipa_axum::{
 
 use crate::{
     AppState,
+    encoded_id::EncodedId,
     errors::Error,
     extractors::Validated,
     features::{
@@ -29,8 +31,8 @@ use crate::{
             User,
         },
         devices::{
-            AuthDevice,
             Device,
+            TelemetryWriteToken,
         },
     },
     responses::{
@@ -38,15 +40,23 @@ use crate::{
         GetDeviceTelemetry,
         GetOneTelemetry,
         GetTelemetry,
+        GetTelemetryAggregate,
         PostTelemetry,
+        PostTelemetryBatch,
     },
 };
 
 mod db;
 
-use db::CreateTelemetry;
+use db::{
+    CreateTelemetry,
+    CreateTelemetryBatch,
+};
 
-pub use db::Telemetry;
+pub use db::{
+    Telemetry,
+    TelemetryBucket,
+};
 
 pub const TAG: &str = "Telemetry";
 
@@ -55,6 +65,8 @@ pub fn router() -> OpenApiRouter<AppState> {
         .routes(routes!(get))
         .routes(routes!(get_all, post))
         .routes(routes!(get_by_device, delete))
+        .routes(routes!(post_batch))
+        .routes(routes!(aggregate))
 }
 
 /// Get telemetry entry by ID
@@ -72,8 +84,9 @@ pub fn router() -> OpenApiRouter<AppState> {
 pub async fn get(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(id): Path<i64>,
+    Path(id): Path<EncodedId>,
 ) -> Result<Json<Telemetry>, Error> {
+    let id = id.decode().ok_or(Error::TelemetryNotFound)?;
     let telemetry = Telemetry::get_by_id(&state.pool, id).await?;
     let device = Device::get_by_id(&state.pool, telemetry.device_id).await?;
 
@@ -144,9 +157,10 @@ pub async fn get_all(
 pub async fn get_by_device(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(device_id): Path<i64>,
+    Path(device_id): Path<EncodedId>,
     Query(timeframe): Query<TelemetryTimeframe>,
 ) -> Result<Json<Vec<Telemetry>>, Error> {
+    let device_id = device_id.decode().ok_or(Error::DeviceNotFound)?;
     let device = Device::get_by_id(&state.pool, device_id).await?;
 
     match auth.role {
@@ -166,25 +180,119 @@ pub async fn get_by_device(
 
 /// Create telemetry entry
 ///
-/// Called by devices using their key authentication.
+/// Called by devices holding a `telemetry:write`-scoped token, obtained
+/// from `POST /devices/{id}/token`.
 #[utoipa::path(
     post,
     path = "",
     request_body = CreateTelemetry,
     responses(PostTelemetry),
     tag = TAG,
-    security(("api_key" = []))
+    security(("device_token" = []))
 )]
 pub async fn post(
     State(state): State<AppState>,
-    AuthDevice(device): AuthDevice,
+    token: TelemetryWriteToken,
     Validated(data): Validated<CreateTelemetry>,
 ) -> Result<(StatusCode, Json<Telemetry>), Error> {
-    let telemetry = Telemetry::create(&state.pool, device.id, data).await?;
+    let telemetry = Telemetry::create(&state.pool, token.device_id, data).await?;
+
+    Ok((StatusCode::CREATED, Json(telemetry)))
+}
+
+/// Create many telemetry entries in one request
+///
+/// Called by devices holding a `telemetry:write`-scoped token. Avoids the
+/// round-trip cost of one `POST /telemetry` call per event for devices that
+/// sample frequently.
+#[utoipa::path(
+    post,
+    path = "/batch",
+    request_body = CreateTelemetryBatch,
+    responses(PostTelemetryBatch),
+    tag = TAG,
+    security(("device_token" = []))
+)]
+pub async fn post_batch(
+    State(state): State<AppState>,
+    token: TelemetryWriteToken,
+    Validated(data): Validated<CreateTelemetryBatch>,
+) -> Result<(StatusCode, Json<Vec<Telemetry>>), Error> {
+    let telemetry = Telemetry::create_batch(&state.pool, token.device_id, data).await?;
 
     Ok((StatusCode::CREATED, Json(telemetry)))
 }
 
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TelemetryAggregateQuery {
+    /// Start time for telemetry data (RFC3339 format)
+    #[param(example = "2025-12-10T00:00:00Z")]
+    pub start: DateTime<Utc>,
+
+    /// End time for telemetry data (RFC3339 format)
+    #[param(example = "2025-12-31T00:00:00Z")]
+    pub end: DateTime<Utc>,
+
+    /// Bucket width in seconds, e.g. 300 for 5-minute buckets, 3600 for hourly
+    #[param(example = 3600)]
+    pub bucket_seconds: i64,
+
+    /// How to reduce each bucket's readings: `avg` (default), `min`, `max`,
+    /// `first`, or `last`
+    #[param(example = "avg")]
+    pub agg: Option<String>,
+}
+
+/// Get time-bucketed telemetry rollups for a device
+///
+/// Returns one reduced point per bucket for brightness/color_temp/ambient_light
+/// (via `agg`, default average) plus a motion event count, so dashboards can
+/// render history without pulling every raw row through
+/// `GET /telemetry/device/{device_id}`.
+///
+/// - Owner can aggregate telemetry for their devices and their Users' public devices.
+/// - User can aggregate telemetry for their devices and their Owner's public devices.
+#[utoipa::path(
+    get,
+    path = "/device/{device_id}/aggregate",
+    params(TelemetryAggregateQuery),
+    responses(GetTelemetryAggregate),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn aggregate(
+    State(state): State<AppState>,
+    auth: Authenticated,
+    Path(device_id): Path<EncodedId>,
+    Query(query): Query<TelemetryAggregateQuery>,
+) -> Result<Json<Vec<TelemetryBucket>>, Error> {
+    let device_id = device_id.decode().ok_or(Error::DeviceNotFound)?;
+    let device = Device::get_by_id(&state.pool, device_id).await?;
+
+    match auth.role {
+        Role::Admin => (),
+        Role::Owner | Role::User(_) if device.owner_id == auth.id => (),
+        Role::User(parent) if device.owner_id == parent && device.is_public => (),
+        Role::Owner
+            if device.is_public
+                && User::is_child(&state.pool, device.owner_id, auth.id).await? => {}
+        _ => return Err(Error::DeviceNotFound),
+    }
+
+    let agg = db::AggFunction::parse(query.agg.as_deref())?;
+    let buckets = Telemetry::aggregate(
+        &state.pool,
+        device_id,
+        query.start,
+        query.end,
+        Duration::seconds(query.bucket_seconds),
+        agg,
+    )
+    .await?;
+
+    Ok(Json(buckets))
+}
+
 /// Delete device telemetry entries
 ///
 /// Owner or User may delete **only** their own telemetry.
@@ -199,9 +307,10 @@ pub async fn post(
 pub async fn delete(
     State(state): State<AppState>,
     auth: Authenticated,
-    Path(device_id): Path<i64>,
+    Path(device_id): Path<EncodedId>,
     Query(timeframe): Query<TelemetryTimeframe>,
 ) -> Result<Json<u64>, Error> {
+    let device_id = device_id.decode().ok_or(Error::DeviceNotFound)?;
     let device = Device::get_by_id(&state.pool, device_id).await?;
 
     match auth.role {
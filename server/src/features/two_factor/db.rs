@@ -0,0 +1,190 @@
+use rand::{
+    Rng,
+    distr::Alphanumeric,
+};
+use sqlx::PgPool;
+use totp_rs::{
+    Algorithm,
+    Secret,
+    TOTP,
+};
+
+use crate::{
+    errors::Error,
+    password::Argon2Config,
+};
+
+pub struct UserTotp {
+    pub user_id: i64,
+    pub secret: String,
+    pub enabled: bool,
+    pub recovery_codes: Vec<String>,
+    pub last_verified_step: Option<i64>,
+}
+
+/// Number of single-use recovery codes issued on activation
+const RECOVERY_CODE_COUNT: usize = 8;
+
+fn build_totp(secret: &str, username: &str) -> Result<TOTP, Error> {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_owned())
+            .to_bytes()
+            .map_err(|e| Error::DataCorruption(e.to_string()))?,
+        Some("LumiRum".to_owned()),
+        username.to_owned(),
+    )
+    .map_err(|e| Error::DataCorruption(e.to_string()))
+}
+
+fn generate_recovery_code() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(char::from)
+        .collect()
+}
+
+impl UserTotp {
+    /// Start (or restart) enrollment with a freshly generated secret
+    pub async fn enroll(pool: &PgPool, user_id: i64) -> Result<Self, Error> {
+        let secret = Secret::generate_secret().to_encoded().to_string();
+
+        sqlx::query_as!(
+            Self,
+            "INSERT INTO user_totp (user_id, secret)
+             VALUES ($1, $2)
+             ON CONFLICT (user_id) DO UPDATE SET secret = $2, enabled = false, recovery_codes = '{}'
+             RETURNING user_id, secret, enabled, recovery_codes, last_verified_step",
+            user_id,
+            secret
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn get(pool: &PgPool, user_id: i64) -> Result<Option<Self>, Error> {
+        sqlx::query_as!(
+            Self,
+            "SELECT user_id, secret, enabled, recovery_codes, last_verified_step
+             FROM user_totp WHERE user_id = $1",
+            user_id
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub fn provisioning_uri(&self, username: &str) -> Result<String, Error> {
+        Ok(build_totp(&self.secret, username)?.get_url())
+    }
+
+    /// Activates 2FA after checking `code`, returning the plaintext recovery codes
+    pub async fn activate(
+        pool: &PgPool,
+        user_id: i64,
+        username: &str,
+        code: &str,
+        argon2: Argon2Config,
+    ) -> Result<Vec<String>, Error> {
+        let totp = Self::get(pool, user_id)
+            .await?
+            .ok_or(Error::TotpNotEnrolled)?;
+
+        if totp.enabled {
+            return Err(Error::TotpAlreadyEnabled);
+        }
+
+        if !build_totp(&totp.secret, username)?.check_current(code).unwrap_or(false) {
+            return Err(Error::TotpCodeInvalid);
+        }
+
+        let plain_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+        let mut hashed_codes = Vec::with_capacity(plain_codes.len());
+        for code in &plain_codes {
+            hashed_codes.push(argon2.hash(code)?);
+        }
+
+        sqlx::query!(
+            "UPDATE user_totp SET enabled = true, recovery_codes = $1 WHERE user_id = $2",
+            &hashed_codes,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(plain_codes)
+    }
+
+    /// Verifies a TOTP code, rejecting replays of the same 30s time step
+    pub async fn verify_code(pool: &PgPool, user_id: i64, username: &str, code: &str) -> Result<bool, Error> {
+        let Some(totp) = Self::get(pool, user_id).await? else {
+            return Ok(false);
+        };
+        if !totp.enabled {
+            return Ok(false);
+        }
+
+        let now = chrono::Utc::now().timestamp().cast_unsigned();
+        let current_step = (now / 30).cast_signed();
+        if totp.last_verified_step == Some(current_step) {
+            return Ok(false);
+        }
+
+        let generator = build_totp(&totp.secret, username)?;
+        if !generator.check(code, now) {
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "UPDATE user_totp SET last_verified_step = $1 WHERE user_id = $2",
+            current_step,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Verifies and consumes a single-use recovery code
+    pub async fn verify_recovery_code(
+        pool: &PgPool,
+        user_id: i64,
+        code: &str,
+        argon2: Argon2Config,
+    ) -> Result<bool, Error> {
+        let Some(totp) = Self::get(pool, user_id).await? else {
+            return Ok(false);
+        };
+
+        let Some(matched) =
+            totp.recovery_codes.iter().find(|hash| argon2.verify(code, hash).is_ok())
+        else {
+            return Ok(false);
+        };
+        let matched = matched.clone();
+
+        let remaining: Vec<String> = totp
+            .recovery_codes
+            .into_iter()
+            .filter(|hash| *hash != matched)
+            .collect();
+
+        sqlx::query!(
+            "UPDATE user_totp SET recovery_codes = $1 WHERE user_id = $2",
+            &remaining,
+            user_id
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(true)
+    }
+}
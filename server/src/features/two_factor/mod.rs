@@ -0,0 +1,181 @@
+use axum::{
+    Json,
+    extract::{
+        ConnectInfo,
+        State,
+    },
+    http::{
+        HeaderMap,
+        StatusCode,
+    },
+};
+use garde::Validate;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use std::net::SocketAddr;
+use utoipa::ToSchema;
+use utoipa_axum::{
+    router::OpenApiRouter,
+    routes,
+};
+
+use crate::{
+    AppState,
+    errors::Error,
+    extractors::Validated,
+    features::auth::{
+        AuthResponse,
+        Session,
+        TwoFactorPending,
+        User,
+        client_ip,
+        device_info,
+        sign,
+    },
+    responses::{
+        ActivateTotp,
+        EnrollTotp,
+        VerifyTwoFactor,
+    },
+};
+
+mod db;
+
+use db::UserTotp;
+
+pub const TAG: &str = "Two-Factor Authentication";
+
+/// Whether the given user has two-factor authentication enabled
+pub async fn is_enabled(pool: &sqlx::PgPool, user_id: i64) -> Result<bool, Error> {
+    Ok(UserTotp::get(pool, user_id).await?.is_some_and(|t| t.enabled))
+}
+
+pub fn router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(enroll))
+        .routes(routes!(activate))
+        .routes(routes!(verify))
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EnrollTotpResponse {
+    /// `otpauth://` URI to render as a QR code
+    pub provisioning_uri: String,
+}
+
+/// Begin TOTP enrollment
+///
+/// Generates a new secret for the authenticated user; nothing is enforced
+/// until the code is confirmed via `POST /2fa/totp/activate`.
+#[utoipa::path(
+    post,
+    path = "/totp/enroll",
+    responses(EnrollTotp),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn enroll(
+    State(state): State<AppState>,
+    auth: crate::features::auth::Authenticated,
+) -> Result<Json<EnrollTotpResponse>, Error> {
+    let user = User::get_by_id(&state.pool, auth.id).await?;
+    let totp = UserTotp::enroll(&state.pool, auth.id).await?;
+
+    Ok(Json(EnrollTotpResponse {
+        provisioning_uri: totp.provisioning_uri(&user.username)?,
+    }))
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct ActivateTotpRequest {
+    /// Current 6-digit code from the authenticator app
+    #[garde(length(chars, equal = 6))]
+    #[schema(min_length = 6, max_length = 6, example = "123456")]
+    pub code: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ActivateTotpResponse {
+    /// Single-use recovery codes; shown only once
+    pub recovery_codes: Vec<String>,
+}
+
+/// Confirm TOTP enrollment and enable two-factor authentication
+#[utoipa::path(
+    post,
+    path = "/totp/activate",
+    request_body = ActivateTotpRequest,
+    responses(ActivateTotp),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn activate(
+    State(state): State<AppState>,
+    auth: crate::features::auth::Authenticated,
+    Validated(payload): Validated<ActivateTotpRequest>,
+) -> Result<Json<ActivateTotpResponse>, Error> {
+    let user = User::get_by_id(&state.pool, auth.id).await?;
+    let recovery_codes =
+        UserTotp::activate(&state.pool, auth.id, &user.username, &payload.code, state.argon2)
+            .await?;
+
+    Ok(Json(ActivateTotpResponse { recovery_codes }))
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct VerifyTwoFactorRequest {
+    /// A current TOTP code, or one of the recovery codes
+    #[garde(length(chars, min = 1))]
+    #[schema(example = "123456")]
+    pub code: String,
+}
+
+/// Complete a two-factor login
+///
+/// Accepts the short-lived "2fa-pending" token minted by `/auth/login`
+/// plus a TOTP or recovery code, and issues a normal access token.
+#[utoipa::path(
+    post,
+    path = "/verify",
+    request_body = VerifyTwoFactorRequest,
+    responses(VerifyTwoFactor),
+    tag = TAG,
+    security(("jwt" = []))
+)]
+pub async fn verify(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    pending: TwoFactorPending,
+    Validated(payload): Validated<VerifyTwoFactorRequest>,
+) -> Result<(StatusCode, Json<AuthResponse>), Error> {
+    let user = User::get_by_id(&state.pool, pending.id).await?;
+
+    let ok = UserTotp::verify_code(&state.pool, pending.id, &user.username, &payload.code).await?
+        || UserTotp::verify_recovery_code(&state.pool, pending.id, &payload.code, state.argon2)
+            .await?;
+
+    if !ok {
+        return Err(Error::TotpCodeInvalid);
+    }
+
+    if user.blocked {
+        return Err(Error::AccountBlocked);
+    }
+
+    let session = Session::create(
+        &state.pool,
+        user.id,
+        device_info(&headers),
+        Some(client_ip(addr)),
+    )
+    .await?;
+    let token = sign(user.id, user.role, &session.jti, &state.jwt)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(AuthResponse::new(user, token, Some(session.refresh_token))),
+    ))
+}
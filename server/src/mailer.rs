@@ -0,0 +1,53 @@
+use lettre::{
+    AsyncSmtpTransport,
+    AsyncTransport,
+    Message,
+    Tokio1Executor,
+    message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+};
+
+#[derive(Clone)]
+pub struct Mailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+}
+
+impl Mailer {
+    /// Builds a mailer from `SMTP_URL`/`SMTP_FROM` environment variables
+    pub fn from_env() -> Result<Self, String> {
+        let url = std::env::var("SMTP_URL").map_err(|e| e.to_string())?;
+        let from = std::env::var("SMTP_FROM").map_err(|e| e.to_string())?;
+
+        let transport = if let (Ok(user), Ok(pass)) =
+            (std::env::var("SMTP_USER"), std::env::var("SMTP_PASS"))
+        {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&url)
+                .map_err(|e| e.to_string())?
+                .credentials(Credentials::new(user, pass))
+                .build()
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&url)
+                .map_err(|e| e.to_string())?
+                .build()
+        };
+
+        Ok(Self { transport, from })
+    }
+
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), String> {
+        let email = Message::builder()
+            .from(self.from.parse().map_err(|e: lettre::address::AddressError| e.to_string())?)
+            .to(to.parse().map_err(|e: lettre::address::AddressError| e.to_string())?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(|e| e.to_string())?;
+
+        self.transport
+            .send(email)
+            .await
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
@@ -8,21 +8,40 @@ use tracing_subscriber::{
 use std::process::exit;
 
 mod database;
+mod encoded_id;
 mod errors;
 mod extractors;
+mod mailer;
+mod password;
 mod responses;
 mod router;
 mod features {
+    pub mod audit;
     pub mod auth;
+    pub mod auth_requests;
+    pub mod circadian;
+    pub mod device_commands;
     pub mod devices;
+    pub mod invites;
+    pub mod password_reset;
     pub mod profiles;
     pub mod system;
+    pub mod telemetry;
+    pub mod two_factor;
 }
 
 #[derive(Clone)]
 struct AppState {
     pool: sqlx::PgPool, // pool cloning is cheap
-    jwt_secret: String,
+    jwt: features::auth::JwtConfig,
+    oauth: features::auth::OAuthConfig,
+    mailer: mailer::Mailer,
+    revoked_jtis: features::auth::RevocationCache,
+    argon2: password::Argon2Config,
+    /// Whether `/auth/register` accepts requests without an invite code
+    open_registration: bool,
+    /// Delivery mechanism for schedule-changed push notifications to devices
+    push: std::sync::Arc<dyn features::devices::PushTransport>,
 }
 
 #[tokio::main]
@@ -45,12 +64,43 @@ async fn main() {
     );
     tracing::info!("database pool is up");
 
-    let jwt_secret = exit_on_error(
-        std::env::var("JWT_SECRET"),
-        "could not get JWT_SECRET environment variable",
+    let jwt = exit_on_error(
+        features::auth::JwtConfig::from_env(),
+        "failed to configure jwt signing keys",
     );
 
-    let state = AppState { pool, jwt_secret };
+    let oauth = exit_on_error(
+        features::auth::OAuthConfig::from_env(),
+        "failed to configure oauth providers",
+    );
+
+    exit_on_error(
+        encoded_id::init(std::env::var("ID_ALPHABET").ok()),
+        "failed to configure id codec",
+    );
+
+    let mailer = exit_on_error(mailer::Mailer::from_env(), "failed to configure mailer");
+
+    let argon2 = exit_on_error(
+        password::Argon2Config::from_env(),
+        "failed to configure argon2 parameters",
+    );
+
+    // Defaults to open; set OPEN_REGISTRATION=false to require an invite code
+    let open_registration = std::env::var("OPEN_REGISTRATION")
+        .map(|v| v != "false")
+        .unwrap_or(true);
+
+    let state = AppState {
+        pool,
+        jwt,
+        oauth,
+        mailer,
+        revoked_jtis: features::auth::RevocationCache::default(),
+        argon2,
+        open_registration,
+        push: std::sync::Arc::new(features::devices::LogPushTransport),
+    };
     let router = router::router().with_state(state);
 
     // Support for `systemfd --no-pid -s http::3000 -- cargo watch -x run`
@@ -80,7 +130,11 @@ async fn main() {
     }
 
     exit_on_error(
-        axum::serve(listener, router).await,
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await,
         "server exited with error",
     );
 }
@@ -0,0 +1,78 @@
+use argon2::{
+    Algorithm,
+    Argon2,
+    Params,
+    PasswordHash,
+    PasswordHasher,
+    PasswordVerifier,
+    Version,
+    password_hash::{
+        SaltString,
+        rand_core::OsRng,
+    },
+};
+
+use crate::errors::Error;
+
+/// Argon2 cost parameters, tunable via environment so operators can raise
+/// them over time without invalidating already-issued password hashes
+#[derive(Clone, Copy)]
+pub struct Argon2Config {
+    params: Params,
+}
+
+impl Argon2Config {
+    /// Reads `ARGON2_MEMORY_KIB`/`ARGON2_ITERATIONS`/`ARGON2_PARALLELISM`,
+    /// falling back to argon2's own defaults for whichever are unset
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = Params::default();
+
+        let m_cost = env_or_default("ARGON2_MEMORY_KIB", defaults.m_cost())?;
+        let t_cost = env_or_default("ARGON2_ITERATIONS", defaults.t_cost())?;
+        let p_cost = env_or_default("ARGON2_PARALLELISM", defaults.p_cost())?;
+
+        let params = Params::new(m_cost, t_cost, p_cost, None).map_err(|e| e.to_string())?;
+        Ok(Self { params })
+    }
+
+    fn argon2(self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params)
+    }
+
+    /// Hashes `password` with the current parameters
+    pub fn hash(self, password: &str) -> Result<String, Error> {
+        Ok(self
+            .argon2()
+            .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))?
+            .to_string())
+    }
+
+    /// Verifies `password` against `stored_hash`. If the hash was produced
+    /// with parameters other than the current ones, returns a freshly-hashed
+    /// replacement that the caller should persist; `Ok(None)` means the hash
+    /// is already up to date.
+    pub fn verify(self, password: &str, stored_hash: &str) -> Result<Option<String>, Error> {
+        let parsed = PasswordHash::new(stored_hash)?;
+        self.argon2().verify_password(password.as_bytes(), &parsed)?;
+
+        // Only the costs are compared; other fields of `Params` parsed from a
+        // hash (such as its output length) need not match the configured ones
+        if Params::try_from(&parsed).is_ok_and(|stored| {
+            stored.m_cost() == self.params.m_cost()
+                && stored.t_cost() == self.params.t_cost()
+                && stored.p_cost() == self.params.p_cost()
+        }) {
+            return Ok(None);
+        }
+
+        Ok(Some(self.hash(password)?))
+    }
+}
+
+fn env_or_default(key: &str, default: u32) -> Result<u32, String> {
+    match std::env::var(key) {
+        Ok(val) => val.parse().map_err(|_| format!("{key} must be a number")),
+        Err(std::env::VarError::NotPresent) => Ok(default),
+        Err(e) => Err(e.to_string()),
+    }
+}
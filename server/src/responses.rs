@@ -1,14 +1,34 @@
 #![allow(unused)]
 use crate::features::{
+    audit::AuditEntry,
     auth::{
         AuthResponse,
+        DeleteTokenResponse,
+        LoginOutcome,
+        Session,
         User,
     },
+    auth_requests::{
+        CreateAuthRequestResponse,
+        PendingAuthRequest,
+    },
     circadian::LightingSchedule,
-    devices::Device,
+    device_commands::DeviceCommand,
+    devices::{
+        Device,
+        DeviceTokenResponse,
+    },
+    invites::InviteResponse,
     profiles::Profile,
     system::Stats,
-    telemetry::Telemetry,
+    telemetry::{
+        Telemetry,
+        TelemetryBucket,
+    },
+    two_factor::{
+        ActivateTotpResponse,
+        EnrollTotpResponse,
+    },
 };
 use error_set::error_set;
 use utoipa::{
@@ -93,6 +113,33 @@ error_set! {
         #[response(status = OK)]
         Success(Vec<User>),
     }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PatchUser := InternalServerError || Unauthorized || {
+        /// User updated successfully
+        #[response(status = OK)]
+        Success(User),
+        /// User does not exist
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RestoreUser := InternalServerError || Unauthorized || {
+        /// User restored successfully
+        #[response(status = OK)]
+        Success(User),
+        /// User does not exist, or was never deleted
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PurgeUser := InternalServerError || Unauthorized || {
+        /// User permanently deleted
+        #[response(status = NO_CONTENT)]
+        Success,
+    }
 
 
     // ME
@@ -106,19 +153,54 @@ error_set! {
         /// User role cannot create a user
         #[response(status = FORBIDDEN)]
         UserCantUser(ErrorResponse),
+        /// Open registration is disabled and no invite code was supplied
+        #[response(status = FORBIDDEN)]
+        InviteRequired(ErrorResponse),
+        /// Invite code does not exist
+        #[response(status = NOT_FOUND)]
+        InviteNotFound(ErrorResponse),
+        /// Invite code has expired
+        #[response(status = GONE)]
+        InviteExpired(ErrorResponse),
+        /// Invite code has reached its use limit
+        #[response(status = CONFLICT)]
+        InviteExhausted(ErrorResponse),
     }
     #[derive(IntoResponses)]
     #[skip(Error,Display,Debug)]
     Login := ValidInternalAuth || {
-        /// Login successful
+        /// Login successful, or a pending two-factor token if 2FA is enabled
         #[response(status = OK)]
-        Success(AuthResponse),
+        Success(LoginOutcome),
         /// Username not found
         #[response(status = NOT_FOUND)]
         NotFound(ErrorResponse),
     }
     #[derive(IntoResponses)]
     #[skip(Error,Display,Debug)]
+    OAuthAuthorize := InternalServerError || {
+        /// Redirect to the provider's authorization endpoint
+        #[response(status = TEMPORARY_REDIRECT)]
+        Redirect,
+        /// Unknown oauth provider
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    OAuthCallback := InternalServerError || {
+        /// OAuth login successful
+        #[response(status = OK)]
+        Success(AuthResponse),
+        /// Unknown oauth provider
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+        /// State parameter is invalid, expired, or does not match
+        #[response(status = UNAUTHORIZED)]
+        StateMismatch(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
     GetMe := InternalServerError || Unauthorized || {
         /// Login successful
         #[response(status = OK)]
@@ -141,6 +223,190 @@ error_set! {
         #[response(status = CONFLICT)]
         Admin(ErrorResponse),
     }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RequestAccountDeletion := ValidInternalAuth || {
+        /// Deletion token issued successfully
+        #[response(status = OK)]
+        Success(DeleteTokenResponse),
+    }
+
+
+    // SESSIONS
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    Refresh := Validation || InternalServerError || {
+        /// New access/refresh token pair issued
+        #[response(status = OK)]
+        Success(AuthResponse),
+        /// Refresh token is invalid, expired, or already used
+        #[response(status = UNAUTHORIZED)]
+        Invalid(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ListSessions := InternalServerError || Unauthorized || {
+        /// Got active sessions successfully
+        #[response(status = OK)]
+        Success(Vec<Session>),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RevokeSession := InternalServerError || Unauthorized || {
+        /// Session revoked successfully
+        #[response(status = NO_CONTENT)]
+        Success,
+        /// Session does not exist
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RevokeAllSessions := InternalServerError || Unauthorized || {
+        /// All sessions revoked successfully
+        #[response(status = NO_CONTENT)]
+        Success,
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    Logout := InternalServerError || Unauthorized || {
+        /// Current session revoked successfully
+        #[response(status = NO_CONTENT)]
+        Success,
+    }
+
+
+    // INVITES
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    CreateInvite := InternalServerError || Validation || Unauthorized || {
+        /// Invite created successfully
+        #[response(status = CREATED)]
+        Created(InviteResponse),
+        /// Only admins and owners can create invites
+        #[response(status = FORBIDDEN)]
+        UserCantInvite(ErrorResponse),
+    }
+
+
+    // AUTH REQUESTS
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    CreateAuthRequest := InternalServerError || Validation || {
+        /// Auth request created successfully
+        #[response(status = CREATED)]
+        Created(CreateAuthRequestResponse),
+        /// Username not found
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ListAuthRequests := InternalServerError || Unauthorized || {
+        /// Got pending auth requests successfully
+        #[response(status = OK)]
+        Success(Vec<PendingAuthRequest>),
+        /// Only an Owner may list their Users' auth requests
+        #[response(status = FORBIDDEN)]
+        UserCantRespond(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ApproveAuthRequest := InternalServerError || Unauthorized || {
+        /// Auth request approved successfully
+        #[response(status = NO_CONTENT)]
+        Success,
+        /// Auth request does not exist
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RespondAuthRequest := InternalServerError || Unauthorized || {
+        /// Auth request approved or denied successfully
+        #[response(status = NO_CONTENT)]
+        Success,
+        /// Only an Owner may respond to their Users' auth requests
+        #[response(status = FORBIDDEN)]
+        UserCantRespond(ErrorResponse),
+        /// Auth request does not exist
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RedeemAuthRequest := Validation || InternalServerError || {
+        /// Token issued successfully
+        #[response(status = OK)]
+        Success(AuthResponse),
+        /// Auth request does not exist, or the access code is wrong
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+        /// Auth request has expired
+        #[response(status = GONE)]
+        Expired(ErrorResponse),
+        /// Auth request has not been approved yet
+        #[response(status = UNAUTHORIZED)]
+        NotApproved(ErrorResponse),
+    }
+
+
+    // TWO-FACTOR AUTHENTICATION
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    EnrollTotp := InternalServerError || Unauthorized || {
+        /// Enrollment started, provisioning URI returned
+        #[response(status = OK)]
+        Success(EnrollTotpResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ActivateTotp := Validation || InternalServerError || Unauthorized || {
+        /// Two-factor authentication enabled
+        #[response(status = OK)]
+        Success(ActivateTotpResponse),
+        /// No enrollment in progress
+        #[response(status = NOT_FOUND)]
+        NotEnrolled(ErrorResponse),
+        /// Two-factor authentication is already enabled
+        #[response(status = CONFLICT)]
+        AlreadyEnabled(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    VerifyTwoFactor := Validation || InternalServerError || {
+        /// Two-factor login completed, access token issued
+        #[response(status = OK)]
+        Success(AuthResponse),
+        /// Pending token missing/invalid, or the supplied code is wrong
+        #[response(status = UNAUTHORIZED)]
+        Unauthorized(ErrorResponse),
+    }
+
+
+    // PASSWORD RESET
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RequestPasswordReset := Validation || InternalServerError || {
+        /// Always returned, whether or not the username exists
+        #[response(status = OK)]
+        Success,
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ResetPassword := Validation || InternalServerError || {
+        /// Password reset successfully
+        #[response(status = OK)]
+        Success,
+        /// Reset token is invalid
+        #[response(status = UNAUTHORIZED)]
+        Invalid(ErrorResponse),
+    }
 
 
     // PROFILES
@@ -211,6 +477,23 @@ error_set! {
         #[response(status = NOT_FOUND)]
         NotFound(ErrorResponse),
     }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    RestoreProfile := InternalServerError || Unauthorized || {
+        /// Profile restored successfully
+        #[response(status = OK)]
+        Success(Profile),
+        /// Profile does not exist, or was never deleted
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PurgeProfile := InternalServerError || Unauthorized || {
+        /// Profile permanently deleted
+        #[response(status = NO_CONTENT)]
+        Success,
+    }
 
 
     // DEVICES
@@ -265,6 +548,9 @@ error_set! {
         /// Cannot set others' device private
         #[response(status = FORBIDDEN)]
         CantDevicePrivate(ErrorResponse),
+        /// Only a device's owner can set its push token
+        #[response(status = FORBIDDEN)]
+        CantPushToken(ErrorResponse),
     }
     #[derive(IntoResponses)]
     #[skip(Error,Display,Debug)]
@@ -278,6 +564,20 @@ error_set! {
     }
     #[derive(IntoResponses)]
     #[skip(Error,Display,Debug)]
+    IssueDeviceToken := InternalServerError || Unauthorized || DeviceNotFound || {
+        /// Scoped telemetry-push token issued successfully
+        #[response(status = OK)]
+        Success(DeviceTokenResponse),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PollDevice := InternalServerError || Unauthorized || DeviceNotFound || {
+        /// Check-in recorded, returns the device's current lighting schedule
+        #[response(status = OK)]
+        Success(Option<LightingSchedule>),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
     RegenerateDeviceKey := InternalServerError || Unauthorized || DeviceNotFound || {
         /// Key regenerated successfully returns the updated device
         #[response(status = OK)]
@@ -335,4 +635,65 @@ error_set! {
         #[response(status = NOT_FOUND)]
         NotFound(ErrorResponse),
     }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PostTelemetryBatch := ValidInternalAuth || {
+        /// Telemetry entries created successfully
+        #[response(status = CREATED)]
+        Success(Vec<Telemetry>),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    GetTelemetryAggregate := InternalServerError || Unauthorized || Validation || {
+        /// Aggregated telemetry buckets, oldest first
+        #[response(status = OK)]
+        Success(Vec<TelemetryBucket>),
+    }
+
+    // AUDIT
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    ListAuditLog := InternalServerError || Unauthorized || Validation || {
+        /// Audit log entries, newest first
+        #[response(status = OK)]
+        Success(Vec<AuditEntry>),
+        /// Sub-users cannot view the audit log
+        #[response(status = FORBIDDEN)]
+        Forbidden(ErrorResponse),
+    }
+
+    // DEVICE COMMANDS
+
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    GetDeviceCommands := InternalServerError || Unauthorized || {
+        /// Got device commands successfully
+        #[response(status = OK)]
+        Success(Vec<DeviceCommand>),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    PostDeviceCommand := ValidInternalAuth || DeviceNotFound || {
+        /// Command queued successfully
+        #[response(status = CREATED)]
+        Success(DeviceCommand),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    DrainDeviceCommands := InternalServerError || Unauthorized || {
+        /// Pending commands returned and marked delivered
+        #[response(status = OK)]
+        Success(Vec<DeviceCommand>),
+    }
+    #[derive(IntoResponses)]
+    #[skip(Error,Display,Debug)]
+    AckDeviceCommand := InternalServerError || Unauthorized || {
+        /// Command acknowledged successfully
+        #[response(status = OK)]
+        Success(DeviceCommand),
+        /// Device command does not exist, or belongs to a different device
+        #[response(status = NOT_FOUND)]
+        NotFound(ErrorResponse),
+    }
 }
@@ -20,15 +20,20 @@ use utoipa_scalar::{
 
 use crate::{
     features::{
+        audit,
         auth::{
             self,
             AuthResponse,
         },
+        auth_requests,
         circadian::LightingSchedule,
+        device_commands,
         devices::{
             self,
             Device,
         },
+        invites,
+        password_reset,
         profiles::{
             self,
             Profile,
@@ -41,6 +46,7 @@ use crate::{
             self,
             Telemetry,
         },
+        two_factor,
     },
     responses::ErrorResponse,
 };
@@ -60,9 +66,15 @@ struct ApiDoc;
 pub fn router() -> Router<crate::AppState> {
     let (router, api) = OpenApiRouter::with_openapi(ApiDoc::openapi())
         .merge(system::router())
+        .nest("/audit", audit::router())
         .nest("/auth", auth::router()) // TODO: manual user creation
+        .nest("/auth-requests", auth_requests::router())
+        .nest("/invites", invites::router())
+        .nest("/2fa", two_factor::router())
+        .nest("/password", password_reset::router())
         .nest("/profiles", profiles::router())
         .nest("/devices", devices::router())
+        .nest("/device-commands", device_commands::router())
         .nest("/telemetry", telemetry::router())
         .split_for_parts();
 
@@ -83,6 +95,10 @@ impl Modify for SecurityAddon {
                 "api_key",
                 SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))),
             );
+            components.add_security_scheme(
+                "device_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
         }
     }
 }